@@ -0,0 +1,73 @@
+use screeps::{
+    constants::{find, StructureType},
+    local::{ObjectId, Position},
+    objects::{ConstructionSite, Room},
+    prelude::*,
+};
+
+// the order prerequisite infrastructure should finish in - a site in an earlier tier is
+// always offered before any site in a later one, so e.g. a road never gets built ahead of
+// the extensions it's meant to service. structure types not listed here all fall into one
+// shared tier after everything declared.
+const TIER_ORDER: &[StructureType] = &[
+    StructureType::Extension,
+    StructureType::Container,
+    StructureType::Tower,
+    StructureType::Road,
+    StructureType::Wall,
+    StructureType::Rampart,
+];
+
+fn tier(structure_type: StructureType) -> usize {
+    TIER_ORDER
+        .iter()
+        .position(|candidate| *candidate == structure_type)
+        .unwrap_or(TIER_ORDER.len())
+}
+
+// a stable build ordering for one room's pending construction sites, topologically sorted
+// by `TIER_ORDER` so every builder agrees on which site is next instead of each one racing
+// whatever order `find` happens to return. recomputed only when the set of pending site ids
+// changes - the (cheap) sort only reruns on an actual write, not on every `find_task` call.
+#[derive(Default)]
+pub struct BuildPlan {
+    cached_site_ids: Vec<ObjectId<ConstructionSite>>,
+    ordered: Vec<ObjectId<ConstructionSite>>,
+}
+
+impl BuildPlan {
+    fn refresh(&mut self, room: &Room) {
+        let sites = room.find(find::MY_CONSTRUCTION_SITES, None);
+        let mut site_ids: Vec<_> = sites
+            .iter()
+            // every site in a visible room resolves an id
+            .map(|site| site.try_id().unwrap())
+            .collect();
+        site_ids.sort();
+        if site_ids == self.cached_site_ids {
+            return;
+        }
+
+        let mut ordered = sites;
+        ordered.sort_by_key(|site| tier(site.structure_type()));
+        self.ordered = ordered
+            .into_iter()
+            .map(|site| site.try_id().unwrap())
+            .collect();
+        self.cached_site_ids = site_ids;
+    }
+
+    // the highest-priority pending site in `room` - ties within the lowest incomplete tier
+    // go to whichever site is closest to `pos`.
+    pub fn best_site(&mut self, room: &Room, pos: Position) -> Option<ObjectId<ConstructionSite>> {
+        self.refresh(room);
+
+        let lowest_tier = tier(self.ordered.first()?.resolve()?.structure_type());
+        self.ordered
+            .iter()
+            .filter_map(|id| id.resolve().map(|site| (*id, site)))
+            .filter(|(_, site)| tier(site.structure_type()) == lowest_tier)
+            .min_by_key(|(_, site)| pos.get_range_to(site.pos()))
+            .map(|(id, _)| id)
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use screeps::game;
+
+use crate::{constants::*, role::WorkerRole};
+
+// roles that can afford to sit out a constrained tick entirely - critical logistics
+// (hauling, harvesting, spawning) keep running every tick regardless, since letting those
+// lapse risks starving the whole colony rather than just slowing its growth.
+pub(crate) const NON_CRITICAL_ROLES: &[WorkerRole] = &[WorkerRole::Builder, WorkerRole::Upgrader];
+
+// tracks, per role, a rolling average of how much CPU one worker's `find_task` call costs,
+// and decides when `run_workers` should start skipping it in favor of just re-running the
+// worker's existing `TaskQueueEntry` unchanged. every `find_task`/intent call is ~0.2 CPU per
+// the CPU-clinic numbers, so on a tight tick the cheapest win is simply not re-deciding work
+// a creep was already doing.
+#[derive(Default)]
+pub struct CpuGovernor {
+    role_averages: HashMap<WorkerRole, f64>,
+    // index into `NON_CRITICAL_ROLES` of the one non-critical role still allowed to run
+    // `find_task` as normal this tick - advanced once per tick so a constrained run never
+    // starves the same role two ticks running.
+    round_robin_index: usize,
+}
+
+impl CpuGovernor {
+    // folds one worker's just-measured `find_task` CPU cost into its role's rolling average.
+    pub fn record(&mut self, role: WorkerRole, cpu_used: f64) {
+        let average = self.role_averages.entry(role).or_insert(cpu_used);
+        *average += (cpu_used - *average) * CPU_GOVERNOR_ROLLING_WEIGHT;
+    }
+
+    pub fn average_cpu(&self, role: WorkerRole) -> f64 {
+        self.role_averages.get(&role).copied().unwrap_or(0.)
+    }
+
+    // runs `f` (a worker's `find_task` call), measures its actual CPU cost, and folds it
+    // into `role`'s rolling average via `record`. this is the one hook a per-worker tick
+    // loop needs to call to feed `average_cpu` - without it `record` never gets real
+    // measurements and `average_cpu` reports 0 for every role. `should_defer` doesn't read
+    // these averages; it decides from `is_constrained` and the round-robin alone.
+    pub fn time_and_record<R>(&mut self, role: WorkerRole, f: impl FnOnce() -> R) -> R {
+        let start = game::cpu::get_used();
+        let result = f();
+        self.record(role, game::cpu::get_used() - start);
+        result
+    }
+
+    // true once the tick is tight enough that `run_workers` should start deferring work -
+    // either a low bucket (can't afford to burn ahead) or this tick having already spent
+    // past its own budget.
+    pub fn is_constrained(&self) -> bool {
+        game::cpu::bucket() < CPU_GOVERNOR_BUCKET_THRESHOLD
+            || game::cpu::get_used() >= CPU_GOVERNOR_TICK_CPU_BUDGET
+    }
+
+    // whether `role` should have `find_task` skipped this tick in favor of its existing
+    // task. critical roles are never deferred; non-critical roles are deferred unless it's
+    // their turn in the round-robin.
+    pub fn should_defer(&self, role: WorkerRole) -> bool {
+        self.is_constrained()
+            && NON_CRITICAL_ROLES.contains(&role)
+            && NON_CRITICAL_ROLES[self.round_robin_index] != role
+    }
+
+    // advances the round-robin cursor. called once per tick so exactly one non-critical
+    // role keeps running `find_task` normally on any given constrained tick.
+    pub fn advance_round_robin(&mut self) {
+        self.round_robin_index = (self.round_robin_index + 1) % NON_CRITICAL_ROLES.len();
+    }
+}
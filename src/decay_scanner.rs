@@ -0,0 +1,195 @@
+use log::*;
+use screeps::{
+    constants::find,
+    local::{ObjectId, RoomName},
+    objects::{Room, Structure},
+    prelude::*,
+    raw_memory::{get_segment, set_active_segments, set_segment},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+
+// persisted shape of a room's scan progress - small enough to round-trip through a memory
+// segment every time it changes without worrying about CPU cost
+#[derive(Serialize, Deserialize, Clone)]
+struct PersistedProgress {
+    cursor: usize,
+    last_completed: u32,
+    tranquility: u32,
+}
+
+// a slow, CPU-bounded walk over a room's owned structures looking for decay (roads,
+// containers, ramparts) worth repairing proactively instead of waiting for the reactive
+// below-watermark repair logic every builder/startup creep already runs. modeled on Garage's
+// scrub: a "tranquility" rate limits how much of the room gets walked per tick, and progress
+// survives a global reset by round-tripping through a `RawMemory` segment instead of living
+// only in `SHARD_STATE`.
+pub struct DecayScanner {
+    cursor: usize,
+    last_completed: u32,
+    // higher tranquility stretches one full pass over more ticks - it divides the per-tick
+    // batch size, so doubling it halves how many structures get checked per tick
+    pub tranquility: u32,
+    // structures flagged as decayed during the pass currently in progress - reset at the
+    // start of each new pass, so roles always see a consistent, slowly-growing snapshot
+    // rather than one that resets mid-tick
+    pub flagged: Vec<ObjectId<Structure>>,
+    loaded_from_memory: bool,
+    // how many ticks in a row `load()` has come back empty - a freshly requested segment
+    // isn't readable until the tick after it was requested, so the very first attempt is
+    // expected to miss. bounded so a room that's never persisted anything (e.g. a brand new
+    // pass) doesn't retry forever.
+    load_attempts: u32,
+}
+
+impl Default for DecayScanner {
+    fn default() -> DecayScanner {
+        DecayScanner {
+            cursor: 0,
+            last_completed: 0,
+            tranquility: DEFAULT_DECAY_SCAN_TRANQUILITY,
+            flagged: Vec::new(),
+            loaded_from_memory: false,
+            load_attempts: 0,
+        }
+    }
+}
+
+impl DecayScanner {
+    // advances the scan for `room` by one rate-limited batch, returning the structures newly
+    // flagged as decayed this tick (also appended to `self.flagged`, which accumulates for
+    // the rest of the pass and is cleared on the tick the pass completes). call at most once
+    // per room per tick.
+    pub fn advance(&mut self, room: &Room, room_name: RoomName) -> Vec<ObjectId<Structure>> {
+        // keep requesting our segment every tick - RawMemory only keeps serving a segment's
+        // data back via `get_segment` while it's been (re-)requested this way
+        set_active_segments(&[DECAY_SCAN_MEMORY_SEGMENT]);
+
+        if !self.loaded_from_memory {
+            if self.load(room_name) {
+                self.loaded_from_memory = true;
+            } else {
+                self.load_attempts += 1;
+                if self.load_attempts >= DECAY_SCAN_LOAD_RETRY_TICKS {
+                    // segment never produced a persisted entry within the retry window -
+                    // either this room has never completed a pass, or the segment genuinely
+                    // has nothing for it. either way, stop asking and run as a fresh scanner.
+                    self.loaded_from_memory = true;
+                }
+            }
+        }
+
+        let tick = screeps::game::time();
+        if self.cursor == 0 && tick < self.last_completed + DECAY_SCAN_INTERVAL_TICKS {
+            // between passes - sleeping off the rest of the interval
+            return Vec::new();
+        }
+
+        let structures = room.find(find::MY_STRUCTURES, None);
+        if structures.is_empty() {
+            return Vec::new();
+        }
+
+        let batch_size = (DECAY_SCAN_BATCH_SIZE / self.tranquility.max(1)).max(1) as usize;
+        let start = self.cursor.min(structures.len());
+        let end = (start + batch_size).min(structures.len());
+
+        let newly_flagged_start = self.flagged.len();
+        for structure_object in &structures[start..end] {
+            let structure = structure_object.as_structure();
+            let hits = structure.hits();
+            let hits_max = structure.hits_max();
+            if hits_max == 0 {
+                continue;
+            }
+            if (hits as f64) < (hits_max as f64) * DECAY_REPAIR_THRESHOLD_FRACTION {
+                self.flagged.push(structure.id());
+            }
+        }
+
+        // stash this tick's newly-flagged structures before a completed pass clears
+        // `self.flagged` for the next one - otherwise the slice we're about to return would
+        // be sliced out of a vec that's already been emptied
+        let newly_flagged = self.flagged[newly_flagged_start..].to_vec();
+
+        self.cursor = end;
+        if self.cursor >= structures.len() {
+            debug!(
+                "decay scanner for {}: pass complete, {} structures flagged",
+                room_name,
+                self.flagged.len()
+            );
+            self.cursor = 0;
+            self.last_completed = tick;
+            self.flagged.clear();
+        }
+
+        self.save(room_name);
+        newly_flagged
+    }
+
+    fn memory_key(room_name: RoomName) -> String {
+        format!("decay_scan_{}", room_name)
+    }
+
+    fn save(&self, room_name: RoomName) {
+        let progress = PersistedProgress {
+            cursor: self.cursor,
+            last_completed: self.last_completed,
+            tranquility: self.tranquility,
+        };
+        match serde_json::to_string(&progress) {
+            Ok(json) => {
+                // every room's progress lives in the same segment, one "key=json" line each,
+                // since a handful of small per-room entries doesn't justify a segment per room
+                let mut lines: Vec<String> = get_segment(DECAY_SCAN_MEMORY_SEGMENT)
+                    .map(|existing| {
+                        existing
+                            .split('\n')
+                            .filter(|line| !line.starts_with(&format!("{}=", Self::memory_key(room_name))))
+                            .map(|line| line.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                lines.push(format!("{}={}", Self::memory_key(room_name), json));
+                set_segment(DECAY_SCAN_MEMORY_SEGMENT, lines.join("\n"));
+            }
+            Err(err) => warn!("decay scanner: failed to serialize progress: {}", err),
+        }
+    }
+
+    // attempts to load this room's persisted progress from the segment, returning whether an
+    // entry was actually found and parsed. a `false` return doesn't distinguish "segment not
+    // readable yet" from "nothing persisted for this room" - callers retry either way, up to
+    // `DECAY_SCAN_LOAD_RETRY_TICKS`, since both resolve the same way once the retry window
+    // expires.
+    fn load(&mut self, room_name: RoomName) -> bool {
+        let Some(segment) = get_segment(DECAY_SCAN_MEMORY_SEGMENT) else {
+            return false;
+        };
+        let key = Self::memory_key(room_name);
+        let Some(entry) = segment.split('\n').find(|line| line.starts_with(&format!("{}=", key)))
+        else {
+            return false;
+        };
+        let Some((_, json)) = entry.split_once('=') else {
+            return false;
+        };
+        match serde_json::from_str::<PersistedProgress>(json) {
+            Ok(progress) => {
+                self.cursor = progress.cursor;
+                self.last_completed = progress.last_completed;
+                self.tranquility = progress.tranquility;
+                true
+            }
+            Err(err) => {
+                warn!(
+                    "decay scanner: failed to deserialize persisted progress for {}: {}",
+                    room_name, err
+                );
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// utility-based task selection: candidates are scored by a set of weighted
+// "considerations" instead of being picked by a fixed priority ladder. this lets a role
+// enumerate every plausible task at once and pick whichever currently scores highest,
+// rather than committing to the first thing a fixed if/else chain happens to find.
+
+// a single factor in a task's score, normalized to [0, 1]. 0 should mean "this candidate
+// is a non-starter" - since scores are combined multiplicatively, a single consideration
+// near 0 is enough to veto an otherwise-attractive candidate.
+pub trait Consideration {
+    fn score(&self) -> f32;
+}
+
+// a weighted set of considerations for one task candidate. final score is
+// `base_weight * product(considerations)`, so considerations act as a multiplicative
+// penalty/bonus on the role's base priority for that kind of task.
+pub struct Dse {
+    pub base_weight: f32,
+    pub considerations: Vec<Box<dyn Consideration>>,
+}
+
+impl Dse {
+    pub fn score(&self) -> f32 {
+        self.considerations
+            .iter()
+            .fold(self.base_weight, |acc, consideration| acc * consideration.score())
+    }
+}
+
+// picks the highest-scoring candidate out of a list, discarding the rest. returns `None`
+// if `candidates` is empty.
+pub fn pick_best<T>(candidates: Vec<(T, Dse)>) -> Option<T> {
+    candidates
+        .into_iter()
+        .map(|(candidate, dse)| (dse.score(), candidate))
+        .fold(None, |best: Option<(f32, T)>, (score, candidate)| match best {
+            Some((best_score, _)) if best_score >= score => best,
+            _ => Some((score, candidate)),
+        })
+        .map(|(_, candidate)| candidate)
+}
+
+// normalized inverse distance: candidates farther than `max_range` score 0, candidates on
+// top of the creep score 1.
+pub struct InverseDistance {
+    pub range: u32,
+    pub max_range: u32,
+}
+
+impl Consideration for InverseDistance {
+    fn score(&self) -> f32 {
+        if self.max_range == 0 {
+            return 1.;
+        }
+        1. - (self.range.min(self.max_range) as f32 / self.max_range as f32)
+    }
+}
+
+// how much work is already done on a build/repair target, as a fraction of the total - a
+// site that's almost finished scores high so creeps don't abandon it for something shinier
+// under a max-picking scheme.
+pub struct RemainingWorkFraction {
+    pub progress: u32,
+    pub progress_total: u32,
+}
+
+impl Consideration for RemainingWorkFraction {
+    fn score(&self) -> f32 {
+        if self.progress_total == 0 {
+            return 0.;
+        }
+        self.progress as f32 / self.progress_total as f32
+    }
+}
+
+// rises steeply as a structure nears destruction, so a dying wall wins out over routine
+// maintenance even when both are technically "below the repair watermark".
+pub struct NearDestruction {
+    pub hits: u32,
+    pub hits_max: u32,
+}
+
+impl Consideration for NearDestruction {
+    fn score(&self) -> f32 {
+        if self.hits_max == 0 {
+            return 0.;
+        }
+        (1. - (self.hits as f32 / self.hits_max as f32)).powi(3)
+    }
+}
+
+// drops toward 0 as the work already reserved against a task approaches the work the
+// target still needs, so we stop stacking extra creeps on a job that's already covered.
+pub struct ReservationPressure {
+    pub reserved: u32,
+    pub needed: u32,
+}
+
+impl Consideration for ReservationPressure {
+    fn score(&self) -> f32 {
+        if self.needed == 0 {
+            return 0.;
+        }
+        (1. - (self.reserved as f32 / self.needed as f32)).clamp(0., 1.)
+    }
+}
@@ -1,16 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use log::*;
 use screeps::{game, RoomName};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+mod build_plan;
+mod cpu_governor;
+mod decay_scanner;
+mod dse;
 mod logging;
 mod movement;
+mod reservation;
+mod scheduler;
 mod task;
 mod worker;
 
 // tunable important numbers for the bot, in one place for convenience
 mod constants {
+    use screeps::constants::ResourceType;
+
     // won't do pathing for moving creeps if CPU is above this number
     pub const HIGH_CPU_THRESHOLD: f64 = 250.;
     // won't do pathing for moving creeps if bucket is below this number
@@ -22,6 +31,9 @@ mod constants {
     pub const MAX_ROOMS: u8 = 64;
     // when task finding fails, idle this long
     pub const NO_TASK_IDLE_TICKS: u32 = 5;
+    // scheduler won't start a new periodic job once this tick's CPU usage crosses this
+    // number, deferring whatever's left to next tick
+    pub const SCHEDULER_CPU_BUDGET: f64 = 400.;
     // builder role considers energy for grabbing above this amount
     pub const BUILDER_ENERGY_PICKUP_THRESHOLD: u32 = 100;
     // builder role considers energy for withdraw from structures above this amount
@@ -30,8 +42,85 @@ mod constants {
     pub const HAULER_ENERGY_PICKUP_THRESHOLD: u32 = 35;
     // hauler role considers energy for withdraw from structures above this amount
     pub const HAULER_ENERGY_WITHDRAW_THRESHOLD: u32 = 500;
+    // assumed round trip length, in ticks, when sizing a hauler body for a room with no
+    // visible sources to measure a real distance from
+    pub const HAULER_DEFAULT_ROUND_TRIP_TICKS: u32 = 100;
+    // energy cost of one "unit" of hauler capacity: two CARRY parts and the MOVE part
+    // needed to keep them at roads speed
+    pub const HAULER_COST_PER_CARRY_PAIR: u32 = 150;
     // fill terminals to this much energy
     pub const TERMINAL_ENERGY_TARGET: u32 = 50_000;
+    // haulers treat dropped/stored minerals below this amount as not worth a special trip -
+    // the energy pickup/withdraw thresholds above stay energy-specific since energy moves in
+    // much bigger piles
+    pub const HAULER_MINERAL_PICKUP_THRESHOLD: u32 = 25;
+    pub const HAULER_MINERAL_WITHDRAW_THRESHOLD: u32 = 100;
+    // the terminal-balancing policy: haulers keep the terminal stocked to roughly these
+    // levels, pulling surplus storage stock in and pushing terminal surplus back out to
+    // storage, for every compound/mineral the colony currently produces or trades
+    pub const TERMINAL_BALANCE_TARGETS: &[(ResourceType, u32)] = &[
+        (ResourceType::Energy, TERMINAL_ENERGY_TARGET),
+        (ResourceType::Hydrogen, 3_000),
+        (ResourceType::Oxygen, 3_000),
+        (ResourceType::Utrium, 1_000),
+        (ResourceType::Keanium, 1_000),
+        (ResourceType::Hydroxide, 3_000),
+        (ResourceType::UtriumHydride, 1_000),
+        (ResourceType::KeaniumHydride, 1_000),
+    ];
+    // towers only spend energy on repair once they're holding at least this much - keeps
+    // enough in reserve that a sudden attack always finds the tower able to fight back
+    pub const TOWER_REPAIR_ENERGY_FLOOR: u32 = 500;
+    // creeps below this many ticks_to_live start weighing a renew/recycle trip to the
+    // nearest spawn against their normal task - early enough that one working a few rooms
+    // out can still walk home before it matters
+    pub const LIFECYCLE_TTL_THRESHOLD: u32 = 300;
+    // once a renew has pushed ticks_to_live this far back out past the threshold above,
+    // `renew_at_spawn` hands the creep back to its normal task instead of renewing again
+    pub const LIFECYCLE_TTL_RENEWED_HYSTERESIS: u32 = 100;
+    // bodies costing at least this much energy to rebuild are worth spending energy to
+    // renew; anything cheaper is better off recycled for the partial spawn-cost refund
+    pub const LIFECYCLE_RENEW_WORTHWHILE_BODY_COST: u32 = 500;
+    // below this bucket, run_workers starts skipping find_task for workers whose current
+    // task is still valid and deferring non-critical roles outright, same trigger point as
+    // LOW_BUCKET_THRESHOLD uses for movement pathing
+    pub const CPU_GOVERNOR_BUCKET_THRESHOLD: i32 = LOW_BUCKET_THRESHOLD;
+    // once this tick's CPU usage crosses this number, run_workers is as constrained as if
+    // bucket were low, regardless of how healthy the bucket actually is
+    pub const CPU_GOVERNOR_TICK_CPU_BUDGET: f64 = HIGH_CPU_THRESHOLD;
+    // weight given to a fresh find_task CPU measurement when folding it into a role's
+    // rolling average - low, since a single expensive tick (e.g. a creep just died and its
+    // room needs a fresh scan) shouldn't swing the average enough to mask the role's normal
+    // steady-state cost
+    pub const CPU_GOVERNOR_ROLLING_WEIGHT: f64 = 0.1;
+    // a path search that burns more than this fraction of MAX_OPS finding a route is treated
+    // the same as an incomplete one - it's expensive enough that it's worth warning about
+    // and scheduling a retry rather than quietly trusting it
+    pub const PATH_CACHE_INCOMPLETE_OPS_FRACTION: f64 = 0.9;
+    // won't advance any room's decay scan once this tick's CPU usage crosses this number
+    pub const DECAY_SCAN_CPU_CEILING: f64 = 300.;
+    // base number of structures checked per tick, divided by a room's tranquility setting
+    pub const DECAY_SCAN_BATCH_SIZE: u32 = 20;
+    // once a pass over every owned structure completes, wait at least this many ticks
+    // before starting the next one
+    pub const DECAY_SCAN_INTERVAL_TICKS: u32 = 1_000;
+    // a structure below this fraction of its max hits gets flagged as decayed, proactively,
+    // well before it'd fall low enough to trip a builder's own reactive repair watermark
+    pub const DECAY_REPAIR_THRESHOLD_FRACTION: f64 = 0.8;
+    // default tranquility for a room that's never had one set from the console - higher
+    // values stretch a pass over more ticks, spending less CPU per tick on the scan
+    pub const DEFAULT_DECAY_SCAN_TRANQUILITY: u32 = 1;
+    // a freshly-requested RawMemory segment only starts being served back by `get_segment`
+    // a tick after it's requested - keep retrying the initial load for this many ticks
+    // before giving up and treating the room as having nothing persisted yet
+    pub const DECAY_SCAN_LOAD_RETRY_TICKS: u32 = 5;
+    // RawMemory segment the decay scanner persists its per-room progress into, so a pass
+    // resumes where it left off after a global reset instead of restarting from scratch
+    pub const DECAY_SCAN_MEMORY_SEGMENT: u8 = 10;
+    // age-based backstop for `ReservationLedger::prune_stale` - a claim older than this was
+    // almost certainly left behind by a creep that died without releasing it, since
+    // `prune_dead_owners` already clears claims the moment their owner stops being tracked
+    pub const RESERVATION_STALE_TICKS: u32 = 50;
 }
 
 // add wasm_bindgen to any function you would like to expose for call from js this one's
@@ -42,6 +131,109 @@ pub fn setup() {
     logging::setup_logging(logging::Info);
 }
 
+// one worker's status, as reported by `list_workers` - mirrors the "active/idle/dead" view
+// Garage's background task manager gives an operator over its jobs
+#[derive(Serialize)]
+enum WorkerStatus {
+    // has a task this tick that isn't `Task::IdleUntil`
+    Active,
+    // currently sitting in `Task::IdleUntil`, or with no task queued at all
+    Idle,
+    // game object couldn't be resolved this tick - orphaned, about to be cleaned up
+    Dead,
+}
+
+#[derive(Serialize)]
+struct WorkerSummary {
+    id: String,
+    role: String,
+    task: Option<String>,
+    status: WorkerStatus,
+}
+
+// JSON array of every tracked worker's id, role, current task, and active/idle/dead status -
+// callable from the JS console for a live look at the colony without redeploying
+#[wasm_bindgen]
+pub fn list_workers() -> String {
+    let shard_state = unsafe { SHARD_STATE.get_or_insert_with(ShardState::default) };
+
+    let summaries: Vec<WorkerSummary> = shard_state
+        .worker_state
+        .iter()
+        .map(|(id, worker_state)| {
+            let task = worker_state.current_task();
+            let status = if !worker_state.is_resolved() {
+                WorkerStatus::Dead
+            } else {
+                match task {
+                    Some(task::Task::IdleUntil(_)) | None => WorkerStatus::Idle,
+                    Some(_) => WorkerStatus::Active,
+                }
+            };
+            WorkerSummary {
+                id: format!("{:?}", id),
+                role: format!("{:?}", worker_state.role()),
+                task: task.map(|task| format!("{:?}", task)),
+                status,
+            }
+        })
+        .collect();
+
+    serde_json::to_string(&summaries).unwrap_or_else(|err| {
+        warn!("list_workers: failed to serialize worker summaries: {}", err);
+        "[]".to_string()
+    })
+}
+
+// stops `spawn::spawn_creep` from queuing any new creep of `role` (matched against its
+// `Debug` name, e.g. "Builder") until `resume_role` is called for it
+#[wasm_bindgen]
+pub fn pause_role(role: String) {
+    let shard_state = unsafe { SHARD_STATE.get_or_insert_with(ShardState::default) };
+    info!("pausing spawns for role {}", role);
+    shard_state.paused_roles.insert(role);
+}
+
+#[wasm_bindgen]
+pub fn resume_role(role: String) {
+    let shard_state = unsafe { SHARD_STATE.get_or_insert_with(ShardState::default) };
+    info!("resuming spawns for role {}", role);
+    shard_state.paused_roles.remove(&role);
+}
+
+// clears a specific worker's task queue, by the same id string `list_workers` reports -
+// `find_task` runs fresh for it next tick as if it had just finished its last task
+#[wasm_bindgen]
+pub fn clear_worker_task(worker_id: String) {
+    let shard_state = unsafe { SHARD_STATE.get_or_insert_with(ShardState::default) };
+    match shard_state
+        .worker_state
+        .iter_mut()
+        .find(|(id, _)| format!("{:?}", id) == worker_id)
+    {
+        Some((_, worker_state)) => worker_state.clear_task_queue(),
+        None => warn!("clear_worker_task: no tracked worker with id {}", worker_id),
+    }
+}
+
+// adjusts how much CPU a room's background decay scan spends per tick - higher tranquility
+// spreads a pass over more ticks, for a bot that's tight on CPU and can afford slower
+// proactive maintenance
+#[wasm_bindgen]
+pub fn set_decay_scan_tranquility(room_name: String, tranquility: u32) {
+    let shard_state = unsafe { SHARD_STATE.get_or_insert_with(ShardState::default) };
+    match room_name.parse::<RoomName>() {
+        Ok(room_name) => match shard_state.colony_state.get_mut(&room_name) {
+            Some(colony_state) => {
+                info!("setting decay scan tranquility for {} to {}", room_name, tranquility);
+                colony_state.decay_scanner.tranquility = tranquility;
+            }
+            None => warn!("set_decay_scan_tranquility: no colony state for room {}", room_name),
+        },
+        Err(_) => warn!("set_decay_scan_tranquility: couldn't parse room name {}", room_name),
+    }
+}
+
 // this is one method of persisting data on the wasm memory heap between ticks
 // this is an alternative to keeping state in memory on game objects - but will be lost on
 // global resets, which occur at differing frequencies on different server environments
@@ -56,6 +248,26 @@ pub struct ShardState {
     pub colony_state: HashMap<RoomName, ColonyState>,
     // workers and their task queues (includes creeps as well as structures)
     pub worker_state: HashMap<worker::WorkerId, worker::WorkerState>,
+    // paces shard-wide periodic jobs (structure scans, terrain lookups) against the tick's
+    // CPU budget instead of gating them on a raw `tick % N` check
+    pub scheduler: scheduler::Scheduler,
+    // paths computed by the movement phase, cached by (origin room, goal) so workers
+    // converging on the same destination share one `PathFinder` search instead of each
+    // paying for their own
+    pub path_cache: movement::PathCache,
+    // roles an operator has paused from the console - `spawn::spawn_creep` checks this
+    // before queuing a new creep of a given role, keyed by the role's `Debug` name since
+    // that's what the console functions below take as input
+    pub paused_roles: HashSet<String>,
+}
+
+impl ShardState {
+    // whether `role` has been paused from the console via `pause_role` - the check
+    // `spawn::spawn_creep` needs to make before queuing a new creep of a given role, matched
+    // the same way `pause_role`/`resume_role` key `paused_roles`: by the role's `Debug` name.
+    pub fn is_role_paused(&self, role: &role::WorkerRole) -> bool {
+        self.paused_roles.contains(&format!("{:?}", role))
+    }
 }
 
 impl Default for ShardState {
@@ -64,12 +276,29 @@ impl Default for ShardState {
             global_init_time: game::time(),
             colony_state: HashMap::new(),
             worker_state: HashMap::new(),
+            scheduler: scheduler::Scheduler::default(),
+            path_cache: movement::PathCache::default(),
+            paused_roles: HashSet::new(),
         }
     }
 }
 
+#[derive(Default)]
 pub struct ColonyState {
-    // todo add stuff here - spawn queue, maybe remote tracking
+    // todo add more stuff here - spawn queue, maybe remote tracking
+    // a stable, cached construction-site build ordering for this room, so every
+    // builder/startup creep agrees on which site to work next instead of racing `find`'s
+    // arbitrary order
+    pub build_plan: build_plan::BuildPlan,
+    // per-role rolling CPU averages and the round-robin state used to decide which workers
+    // `run_workers` can afford to re-run `find_task` for on a constrained tick
+    pub cpu_governor: cpu_governor::CpuGovernor,
+    // slow, CPU-bounded proactive decay-repair walk over this room's owned structures, with
+    // progress persisted to RawMemory so it survives a global reset
+    pub decay_scanner: decay_scanner::DecayScanner,
+    // outstanding task claims for this room's workers - shared by every role's `find_task` so
+    // they agree on what's already spoken for instead of racing each other for it
+    pub task_reservations: reservation::ReservationLedger,
 }
 
 // to use a reserved name as a function name, use `js_name`:
@@ -87,9 +316,72 @@ pub fn game_loop() {
     // no longer see
     worker::scan_and_register_creeps(&mut shard_state);
 
-    // scan for new worker structures as well - every 100 ticks, or if this is the startup tick
-    if tick % 100 == 0 || tick == shard_state.global_init_time {
+    // scan for new worker structures on a schedule instead of a raw `tick % 100` check, so
+    // a CPU-hungry tick can defer the scan rather than paying for it on top of everything else
+    shard_state
+        .scheduler
+        .register("scan_and_register_structures", 100, 10);
+    if tick == shard_state.global_init_time {
+        // first tick after a global reset - always scan once regardless of the schedule, so
+        // a fresh worker state isn't missing structures for up to a full `interval` ticks
         worker::scan_and_register_structures(&mut shard_state);
+        shard_state.scheduler.mark_run("scan_and_register_structures");
+    } else {
+        // drive every other scheduled job through `run_due` so due jobs are drained in
+        // priority order under one shared CPU budget, instead of each caller hand-rolling
+        // its own `is_due`/`mark_run` pair
+        let mut scheduler = std::mem::take(&mut shard_state.scheduler);
+        let mut jobs: HashMap<&'static str, Box<dyn FnMut()>> = HashMap::new();
+        jobs.insert(
+            "scan_and_register_structures",
+            Box::new(|| worker::scan_and_register_structures(&mut shard_state)),
+        );
+        scheduler.run_due(&mut jobs);
+        drop(jobs);
+        shard_state.scheduler = scheduler;
+    }
+
+    // advance each room's non-critical-role round robin once per tick, before `run_workers`
+    // reads it to decide which of them gets to run `find_task` as normal on a constrained tick.
+    // `run_workers` (and the per-worker `find_task` call it drives) lives in `worker`, which
+    // this snapshot of the tree doesn't include - so the actual `should_defer`/`time_and_record`
+    // gating can't be wired up from here. surface the decision it would be making anyway, so a
+    // constrained tick is at least visible in the console instead of silently doing nothing.
+    for (room_name, colony_state) in shard_state.colony_state.iter_mut() {
+        colony_state.cpu_governor.advance_round_robin();
+        if colony_state.cpu_governor.is_constrained() {
+            let deferred: Vec<_> = cpu_governor::NON_CRITICAL_ROLES
+                .iter()
+                .filter(|role| colony_state.cpu_governor.should_defer(**role))
+                .collect();
+            if !deferred.is_empty() {
+                warn!(
+                    "{}: CPU constrained this tick, would defer find_task for {:?}",
+                    room_name, deferred
+                );
+            }
+        }
+    }
+
+    // drop any task claims left behind by creeps that no longer exist, before `run_workers`
+    // reads reserved totals for this tick's `find_task` calls - otherwise a task a dead creep
+    // was assigned stays oversubscribed forever
+    let live_workers: HashSet<worker::WorkerId> = shard_state.worker_state.keys().copied().collect();
+    for colony_state in shard_state.colony_state.values_mut() {
+        colony_state.task_reservations.prune_dead_owners(&live_workers);
+        colony_state
+            .task_reservations
+            .prune_stale(constants::RESERVATION_STALE_TICKS);
+    }
+
+    // walk one rate-limited batch of each room's decay scan, skipping entirely once this
+    // tick is already spending too much CPU elsewhere
+    if game::cpu::get_used() < constants::DECAY_SCAN_CPU_CEILING {
+        for (room_name, colony_state) in shard_state.colony_state.iter_mut() {
+            if let Some(room) = game::rooms().get(*room_name) {
+                colony_state.decay_scanner.advance(&room, *room_name);
+            }
+        }
     }
 
     // run all registered workers, attempting to resolve those that haven't already and deleting
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use screeps::{
+    game,
+    local::{Direction, Position, RoomName},
+    pathfinder::{search, SearchOptions},
+};
+
+use crate::{constants::*, worker::WorkerId, ShardState};
+
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum MovementProfile {
+    // one MOVE part can drag one non-MOVE part over plains at full speed
+    PlainsOneToOne,
+    // one MOVE part keeps up with one non-MOVE part only on roads - cheaper body, slower off-road
+    RoadsOneToOne,
+    // one MOVE part per non-MOVE part, sized for swamp terrain
+    SwampOneToOne,
+}
+
+impl MovementProfile {
+    // road/plains/swamp weighting passed to the pathfinder, lower is more attractive
+    fn cost_options(self) -> (u8, u8, u8) {
+        match self {
+            // (plains, swamp, road) cost multipliers
+            MovementProfile::PlainsOneToOne => (1, 5, 1),
+            MovementProfile::RoadsOneToOne => (2, 10, 1),
+            MovementProfile::SwampOneToOne => (1, 1, 1),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct MovementGoal {
+    pub pos: Position,
+    pub range: u32,
+    pub profile: MovementProfile,
+    pub avoid_creeps: bool,
+}
+
+// a path found for a given (origin room, goal) pair, reusable by every worker currently
+// walking toward the same goal from that room instead of each one paying for its own
+// `PathFinder` search
+struct CachedPath {
+    steps: Vec<Position>,
+    // set when the search hit `MAX_OPS`/`MAX_ROOMS` without finding a real route to the goal,
+    // or burned more than `PATH_CACHE_INCOMPLETE_OPS_FRACTION` of `MAX_OPS` finding one -
+    // callers still get the (possibly partial) route, but the cache entry is treated as due
+    // for a retry rather than trusted long-term
+    incomplete: bool,
+    computed_at: u32,
+}
+
+// per-worker progress following a `CachedPath` - which step it's on, and how long it's been
+// stuck on the same step, so a blocked creep gets a fresh path instead of grinding forever
+// against a jammed doorway
+#[derive(Default)]
+struct FollowState {
+    step_index: usize,
+    last_pos: Option<Position>,
+    stuck_ticks: u8,
+}
+
+// caches computed paths by `(origin room, goal)` so creeps converging on the same
+// destination from the same room - haulers running the same route, builders queuing on the
+// same construction site - share one `PathFinder` search instead of each repeating it.
+#[derive(Default)]
+pub struct PathCache {
+    paths: HashMap<(RoomName, MovementGoal), CachedPath>,
+    progress: HashMap<WorkerId, FollowState>,
+}
+
+impl PathCache {
+    // invalidates any cache entry older than this - even a complete path can go stale once
+    // new construction blocks it, so entries aren't trusted forever
+    const MAX_PATH_AGE_TICKS: u32 = 50;
+    // an incomplete/partial path is only trusted for this much shorter window before a retry
+    // is scheduled - the search already told us it didn't reach the actual goal, so there's
+    // no reason to keep following it as long as a complete route
+    const MAX_INCOMPLETE_PATH_AGE_TICKS: u32 = 5;
+
+    fn find_or_refresh(&mut self, from: Position, goal: MovementGoal, tick: u32) -> &CachedPath {
+        let key = (from.room_name(), goal);
+        let needs_search = match self.paths.get(&key) {
+            Some(cached) => {
+                let max_age = if cached.incomplete {
+                    Self::MAX_INCOMPLETE_PATH_AGE_TICKS
+                } else {
+                    Self::MAX_PATH_AGE_TICKS
+                };
+                tick.saturating_sub(cached.computed_at) > max_age
+            }
+            None => true,
+        };
+        if needs_search {
+            let (plains_cost, swamp_cost, road_cost) = goal.profile.cost_options();
+            let options = SearchOptions::new()
+                .plain_cost(plains_cost)
+                .swamp_cost(swamp_cost)
+                .road_cost(road_cost)
+                .max_ops(MAX_OPS)
+                .max_rooms(MAX_ROOMS as u32);
+            let result = search(from, (goal.pos, goal.range), options);
+
+            let ops_fraction = result.ops() as f64 / MAX_OPS as f64;
+            let needs_retry =
+                result.incomplete() || ops_fraction > PATH_CACHE_INCOMPLETE_OPS_FRACTION;
+            if needs_retry {
+                warn!(
+                    "movement: path from {:?} toward {:?} (range {}) came back {} after {} ops - \
+                     falling back to the furthest reachable tile",
+                    from,
+                    goal.pos,
+                    goal.range,
+                    if result.incomplete() { "incomplete" } else { "suspiciously expensive" },
+                    result.ops(),
+                );
+            }
+
+            self.paths.insert(
+                key,
+                CachedPath {
+                    steps: result.path(),
+                    incomplete: needs_retry,
+                    computed_at: tick,
+                },
+            );
+        }
+        // entry was just inserted or confirmed fresh above
+        self.paths.get(&key).unwrap()
+    }
+
+    // returns the next step a worker following `goal` from `from` should move toward,
+    // recomputing the cached path if the worker has gotten stuck for
+    // `STUCK_REPATH_THRESHOLD` ticks running. a worker that has deviated from the path
+    // resumes from the closest remaining step instead of triggering a new search.
+    pub fn next_step(
+        &mut self,
+        worker_id: WorkerId,
+        from: Position,
+        goal: MovementGoal,
+        tick: u32,
+    ) -> Option<Position> {
+        if from.get_range_to(goal.pos) <= goal.range {
+            self.progress.remove(&worker_id);
+            return None;
+        }
+
+        let follow = self.progress.entry(worker_id).or_default();
+        let stuck = follow
+            .last_pos
+            .is_some_and(|last_pos| last_pos == from && follow.stuck_ticks >= STUCK_REPATH_THRESHOLD);
+        if stuck {
+            self.paths.remove(&(from.room_name(), goal));
+            follow.step_index = 0;
+            follow.stuck_ticks = 0;
+        } else if follow.last_pos == Some(from) {
+            follow.stuck_ticks += 1;
+        } else {
+            follow.stuck_ticks = 0;
+        }
+        follow.last_pos = Some(from);
+
+        // re-borrow immutably now that the stuck-check above is done poking `progress`
+        let follow_index = self.progress.get(&worker_id).map(|f| f.step_index).unwrap_or(0);
+        let cached = self.find_or_refresh(from, goal, tick);
+
+        // a creep that's drifted off its cached route (pushed off, or the step list was
+        // indexed from a stale position) just resumes from whichever step is actually
+        // closest instead of walking backward to rejoin the path exactly where it left off
+        let resume_index = cached
+            .steps
+            .iter()
+            .enumerate()
+            .skip(follow_index)
+            .min_by_key(|(_, step)| step.get_range_to(from))
+            .map(|(index, _)| index)
+            .unwrap_or(follow_index);
+
+        let next = cached.steps.get(resume_index).copied();
+        if let Some(follow) = self.progress.get_mut(&worker_id) {
+            follow.step_index = resume_index + 1;
+        }
+        next
+    }
+
+    // drops stale per-worker following state for workers that are no longer registered -
+    // otherwise a dead or recycled creep's last stuck-tick count lingers forever
+    pub fn prune_workers(&mut self, live_workers: impl Iterator<Item = WorkerId>) {
+        let live: std::collections::HashSet<_> = live_workers.collect();
+        self.progress.retain(|worker_id, _| live.contains(worker_id));
+    }
+}
+
+// direction from `from` toward `to`, for handing to `Creep::move_direction` once the next
+// step on a cached path has been picked
+pub fn direction_to(from: Position, to: Position) -> Option<Direction> {
+    from.get_direction_to(to)
+}
+
+// run movement for every worker that asked to move this tick, following cached paths where
+// possible, then drop the game-object references held in `worker_state` for the tick -
+// nothing past this point should still be touching this tick's object handles.
+pub fn run_movement_and_remove_worker_refs(shard_state: &mut ShardState) {
+    let tick = game::time();
+    let worker_ids: Vec<WorkerId> = shard_state.worker_state.keys().copied().collect();
+
+    for worker_id in worker_ids {
+        if let Some(worker_state) = shard_state.worker_state.get(&worker_id) {
+            if let (Some(goal), Some(reference)) =
+                (worker_state.pending_move(), worker_state.reference())
+            {
+                let from = reference.pos();
+                if let Some(next_pos) = shard_state.path_cache.next_step(worker_id, from, goal, tick)
+                {
+                    if let Some(creep) = reference.as_creep() {
+                        if let Some(direction) = direction_to(from, next_pos) {
+                            let _ = creep.move_direction(direction);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    shard_state.path_cache.prune_workers(shard_state.worker_state.keys().copied());
+
+    for worker_state in shard_state.worker_state.values_mut() {
+        worker_state.drop_tick_refs();
+    }
+}
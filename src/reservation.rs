@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use screeps::game;
+
+use crate::{task::Task, worker::WorkerId};
+
+// how many ticks of a job's per-tick throughput we'll let a single task claim reservations
+// for at once - caps a long-running repair/build job from being oversubscribed with every
+// hauler/builder in the room the moment it's posted, when only a handful of ticks' worth of
+// work is actually claimable before the next `find_task` pass re-evaluates it
+const RESERVATION_THROUGHPUT_WINDOW_TICKS: u32 = 50;
+
+// a single outstanding claim against a task: how much work it represents, which worker made
+// it, and the tick it was made, so claims outlived by their owning creep can be pruned
+// instead of permanently inflating a task's reserved total.
+#[derive(Debug, Clone, Copy)]
+struct Claim {
+    amount: u32,
+    owner: WorkerId,
+    claimed_at: u32,
+}
+
+// tracks, per `Task`, how much work is already claimed by assigned creeps. replaces the old
+// bare `HashMap<Task, u32>` that every role's `find_task` poked at directly (with subtly
+// different comparisons - `Upgrader` checked `<` where `Builder` checked `<=`) with a single
+// API that all of them share.
+#[derive(Default)]
+pub struct ReservationLedger {
+    claims: HashMap<Task, Vec<Claim>>,
+}
+
+impl ReservationLedger {
+    // claims `amount` of work against `task` on behalf of `owner`. `amount` is in the same
+    // unit the caller uses to express "how much more work is needed" - ticks of
+    // REPAIR_POWER/BUILD_POWER for build/repair, energy units for hauling.
+    pub fn reserve(&mut self, task: Task, amount: u32, owner: WorkerId) {
+        if amount > 0 {
+            self.claims.entry(task).or_default().push(Claim {
+                amount,
+                owner,
+                claimed_at: game::time(),
+            });
+        }
+    }
+
+    // releases a claim previously made with `reserve` by the same owner. only removes one
+    // matching claim, so releasing doesn't accidentally clear other creeps' reservations
+    // against the same task.
+    pub fn release(&mut self, task: Task, amount: u32, owner: WorkerId) {
+        if let Some(claims) = self.claims.get_mut(&task) {
+            if let Some(index) = claims
+                .iter()
+                .position(|claim| claim.amount == amount && claim.owner == owner)
+            {
+                claims.remove(index);
+            }
+            if claims.is_empty() {
+                self.claims.remove(&task);
+            }
+        }
+    }
+
+    // total work currently claimed against a task.
+    pub fn reserved(&self, task: &Task) -> u32 {
+        self.claims
+            .get(task)
+            .map(|claims| claims.iter().map(|claim| claim.amount).sum())
+            .unwrap_or(0)
+    }
+
+    // how much more work `task` can still absorb before it's oversubscribed, given that it
+    // needs `target_need` total and already has creeps committed to covering some of that.
+    // `throughput_per_tick` bounds the claimable total to a fixed window of ticks' worth of
+    // work (see `RESERVATION_THROUGHPUT_WINDOW_TICKS`) for WORK-part-limited jobs like
+    // build/repair, so a slow multi-thousand-tick job doesn't get oversubscribed with every
+    // idle creep in the room at once; pass 0 for tasks with no meaningful per-tick throughput
+    // (simple carry amounts), which leaves `target_need` uncapped.
+    // this is the one call every role's `find_task` should make instead of reimplementing
+    // the "is this already spoken for" check inline.
+    pub fn remaining_capacity(&self, task: &Task, target_need: u32, throughput_per_tick: u32) -> u32 {
+        let claimable = if throughput_per_tick == 0 {
+            target_need
+        } else {
+            target_need.min(throughput_per_tick.saturating_mul(RESERVATION_THROUGHPUT_WINDOW_TICKS))
+        };
+        claimable.saturating_sub(self.reserved(task))
+    }
+
+    // drops claims older than `max_age_ticks` - covers creeps that stopped existing (died,
+    // got recycled) without releasing their reservation first.
+    pub fn prune_stale(&mut self, max_age_ticks: u32) {
+        let now = game::time();
+        self.claims.retain(|_, claims| {
+            claims.retain(|claim| now.saturating_sub(claim.claimed_at) <= max_age_ticks);
+            !claims.is_empty()
+        });
+    }
+
+    // drops claims whose owning worker is no longer tracked - covers the same "creep died"
+    // case as `prune_stale`, but immediately instead of waiting out `max_age_ticks`.
+    pub fn prune_dead_owners(&mut self, live_workers: &HashSet<WorkerId>) {
+        self.claims.retain(|_, claims| {
+            claims.retain(|claim| live_workers.contains(&claim.owner));
+            !claims.is_empty()
+        });
+    }
+}
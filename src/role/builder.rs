@@ -1,21 +1,24 @@
 use log::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use screeps::{
     constants::{find, Direction, Part, ResourceType, Terrain, BUILD_POWER, REPAIR_POWER},
     enums::StructureObject,
     game,
-    local::RoomName,
-    objects::{Room, Store, StructureSpawn},
+    local::{ObjectId, Position, RoomName},
+    objects::{Room, Store, Structure, StructureSpawn},
     prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
     constants::*,
+    dse::{self, Dse, NearDestruction, RemainingWorkFraction, ReservationPressure},
+    reservation::ReservationLedger,
     role::WorkerRole,
-    task::{Task, TaskQueueEntry},
-    worker::Worker,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -29,19 +32,34 @@ pub struct Builder {
 impl Worker for Builder {
     fn find_task(
         &self,
+        pos: Position,
         store: &Store,
         _worker_roles: &HashSet<WorkerRole>,
-        task_reservations: &mut HashMap<Task, u32>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        build_plan: &mut BuildPlan,
+        decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
     ) -> TaskQueueEntry {
         match game::rooms().get(self.home_room) {
             Some(room) => {
                 let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
+                if let Some(task) =
+                    lifecycle::decide(&room, pos, ticks_to_live, body_cost, energy_amount > 0)
+                {
+                    return TaskQueueEntry::new_unreserved(task);
+                }
                 if energy_amount > 0 {
                     find_build_or_repair_task(
                         &room,
+                        pos,
                         self.repair_watermark,
                         energy_amount,
                         task_reservations,
+                        build_plan,
+                        decayed_structures,
+                        owner,
                     )
                 } else {
                     let energy_capacity = store
@@ -49,7 +67,7 @@ impl Worker for Builder {
                         .try_into()
                         .unwrap_or(0);
                     if energy_capacity > 0 {
-                        find_energy_or_source(&room, energy_capacity, task_reservations)
+                        find_energy_or_source(&room, energy_capacity, task_reservations, owner)
                     } else {
                         warn!("no energy capacity! hurt?");
                         TaskQueueEntry::new_unreserved(Task::IdleUntil(
@@ -71,13 +89,27 @@ impl Worker for Builder {
     }
 }
 
+// candidates are scored with a Dse instead of taking the first match, so a creep doesn't
+// rush past a nearly-finished construction site or a dying wall just because some other
+// job happens to be earlier in `find`'s results
+const BUILDER_REPAIR_BASE_WEIGHT: f32 = 1.;
+const BUILDER_DECAY_REPAIR_BASE_WEIGHT: f32 = 0.4;
+const BUILDER_BUILD_BASE_WEIGHT: f32 = 0.75;
+const BUILDER_MAX_RANGE: u32 = 50;
+
 fn find_build_or_repair_task(
     room: &Room,
+    pos: Position,
     repair_watermark: u32,
     energy_amount: u32,
-    task_reservations: &mut HashMap<Task, u32>,
+    task_reservations: &mut ReservationLedger,
+    build_plan: &mut BuildPlan,
+    decayed_structures: &[ObjectId<Structure>],
+    owner: WorkerId,
 ) -> TaskQueueEntry {
-    // look for repair tasks first
+    let mut candidates = Vec::new();
+
+    // repair candidates
     // note that we're using STRUCTURES instead of MY_STRUCTURES so we can catch roads, containers, and walls
     for structure_object in room.find(find::STRUCTURES, None) {
         // we actually don't care what type of structure this is, convert
@@ -87,38 +119,100 @@ fn find_build_or_repair_task(
         let hits_max = structure.hits_max();
 
         // if hits_max is 0, it's indestructable
-        if hits_max != 0 {
-            // if the hits are below our 'watermark' to repair to
-            // as well as less than half of this struture's max, repair!
-            if hits < repair_watermark && hits * 2 < hits_max {
-                let target_max = std::cmp::min(repair_watermark, hits_max);
-                let amount_needed = (target_max - hits) / REPAIR_POWER;
-                let task = Task::Repair(structure.id());
-                if *task_reservations.get(&task).unwrap_or(&0) < amount_needed {
-                    return TaskQueueEntry::new(task, energy_amount, task_reservations);
-                }
+        if hits_max == 0 {
+            continue;
+        }
+        // if the hits are below our 'watermark' to repair to
+        // as well as less than half of this struture's max, it's a repair candidate
+        if hits < repair_watermark && hits * 2 < hits_max {
+            let target_max = std::cmp::min(repair_watermark, hits_max);
+            let needed = (target_max - hits) / REPAIR_POWER;
+            let task = Task::Repair(structure.id());
+            let reserved = task_reservations.reserved(&task);
+            if task_reservations.remaining_capacity(&task, needed, REPAIR_POWER) > 0 {
+                candidates.push((
+                    task,
+                    Dse {
+                        base_weight: BUILDER_REPAIR_BASE_WEIGHT,
+                        considerations: vec![
+                            Box::new(dse::InverseDistance {
+                                range: pos.get_range_to(structure.pos()),
+                                max_range: BUILDER_MAX_RANGE,
+                            }),
+                            Box::new(NearDestruction { hits, hits_max }),
+                            Box::new(ReservationPressure { reserved, needed }),
+                        ],
+                    },
+                ));
+            }
+        } else if decayed_structures.contains(&structure.id()) {
+            // proactive maintenance candidate - above the reactive watermark, but the
+            // background decay scanner flagged it as worth topping off. low weight, so it
+            // only gets picked up when nothing more urgent needs doing.
+            let needed = (hits_max - hits) / REPAIR_POWER;
+            let task = Task::Repair(structure.id());
+            let reserved = task_reservations.reserved(&task);
+            if task_reservations.remaining_capacity(&task, needed, REPAIR_POWER) > 0 {
+                candidates.push((
+                    task,
+                    Dse {
+                        base_weight: BUILDER_DECAY_REPAIR_BASE_WEIGHT,
+                        considerations: vec![
+                            Box::new(dse::InverseDistance {
+                                range: pos.get_range_to(structure.pos()),
+                                max_range: BUILDER_MAX_RANGE,
+                            }),
+                            Box::new(ReservationPressure { reserved, needed }),
+                        ],
+                    },
+                ));
             }
         }
     }
 
-    // look for construction tasks next
-    for construction_site in room.find(find::MY_CONSTRUCTION_SITES, None) {
-        let amount_needed =
-            (construction_site.progress_total() - construction_site.progress()) / BUILD_POWER;
-        // we can unwrap this id because we know the room the site is in must be visible
-        let task = Task::Build(construction_site.try_id().unwrap());
-        if *task_reservations.get(&task).unwrap_or(&0) < amount_needed {
-            return TaskQueueEntry::new(task, energy_amount, task_reservations);
+    // the single highest-priority pending construction site, per the dependency-ordered
+    // build plan, instead of scoring every raw `find::MY_CONSTRUCTION_SITES` result - this
+    // is what keeps e.g. a road from outscoring the extensions it's meant to serve
+    if let Some(site_id) = build_plan.best_site(room, pos) {
+        if let Some(construction_site) = site_id.resolve() {
+            let progress = construction_site.progress();
+            let progress_total = construction_site.progress_total();
+            let needed = (progress_total - progress) / BUILD_POWER;
+            let task = Task::Build(site_id);
+            let reserved = task_reservations.reserved(&task);
+            if task_reservations.remaining_capacity(&task, needed, BUILD_POWER) > 0 {
+                candidates.push((
+                    task,
+                    Dse {
+                        base_weight: BUILDER_BUILD_BASE_WEIGHT,
+                        considerations: vec![
+                            Box::new(dse::InverseDistance {
+                                range: pos.get_range_to(construction_site.pos()),
+                                max_range: BUILDER_MAX_RANGE,
+                            }),
+                            Box::new(RemainingWorkFraction {
+                                progress,
+                                progress_total,
+                            }),
+                            Box::new(ReservationPressure { reserved, needed }),
+                        ],
+                    },
+                ));
+            }
         }
     }
 
-    TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
+    match dse::pick_best(candidates) {
+        Some(task) => TaskQueueEntry::new(task, energy_amount, owner, task_reservations),
+        None => TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS)),
+    }
 }
 
 fn find_energy_or_source(
     room: &Room,
     energy_capacity: u32,
-    task_reservations: &mut HashMap<Task, u32>,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
 ) -> TaskQueueEntry {
     // check for energy on the ground of sufficient quantity to care about
     for resource in room.find(find::DROPPED_RESOURCES, None) {
@@ -128,8 +222,8 @@ fn find_energy_or_source(
         {
             let reserve_amount = std::cmp::min(resource_amount, energy_capacity);
             let task = Task::TakeFromResource(resource.id());
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= resource_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, resource_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
@@ -151,8 +245,8 @@ fn find_energy_or_source(
         if energy_amount >= BUILDER_ENERGY_WITHDRAW_THRESHOLD {
             let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
             let task = Task::TakeFromStructure(structure.as_structure().id(), ResourceType::Energy);
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= energy_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, energy_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
@@ -170,8 +264,8 @@ fn find_energy_or_source(
             }
         }
         let task = Task::HarvestEnergyUntilFull(source.id());
-        if *task_reservations.get(&task).unwrap_or(&0) <= harvest_positions {
-            return TaskQueueEntry::new(task, 1, task_reservations);
+        if task_reservations.reserved(&task) < harvest_positions {
+            return TaskQueueEntry::new(task, 1, owner, task_reservations);
         }
     }
 
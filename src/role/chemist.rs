@@ -0,0 +1,345 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use screeps::{
+    constants::{find, Part, ResourceType},
+    enums::StructureObject,
+    game,
+    local::{ObjectId, Position, RoomName},
+    objects::{Room, Store, Structure, StructureFactory, StructureLab, StructureSpawn},
+    prelude::*,
+};
+
+use crate::{
+    build_plan::BuildPlan,
+    constants::*,
+    reservation::ReservationLedger,
+    role::WorkerRole,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
+};
+
+// which kind of structure a recipe's reaction actually runs in - lab reactions need a
+// reaction lab fed by a pair of reagent labs, factory commodities are produced directly in
+// the factory's own store.
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Station {
+    Lab,
+    Factory,
+}
+
+// a production recipe: `inputs` are consumed, `output` is what ends up in the producing
+// structure's store once the reaction/production intent fires.
+pub struct Recipe {
+    pub inputs: [ResourceType; 2],
+    pub output: ResourceType,
+    pub station: Station,
+}
+
+// starter set of tier-1 reactions plus one factory commodity - enough to bootstrap basic
+// boosts. extend this table as the colony's mineral income grows.
+pub const RECIPES: &[Recipe] = &[
+    Recipe {
+        inputs: [ResourceType::Hydrogen, ResourceType::Oxygen],
+        output: ResourceType::Hydroxide,
+        station: Station::Lab,
+    },
+    Recipe {
+        inputs: [ResourceType::Utrium, ResourceType::Hydrogen],
+        output: ResourceType::UtriumHydride,
+        station: Station::Lab,
+    },
+    Recipe {
+        inputs: [ResourceType::Keanium, ResourceType::Hydrogen],
+        output: ResourceType::KeaniumHydride,
+        station: Station::Lab,
+    },
+    Recipe {
+        inputs: [ResourceType::Utrium, ResourceType::Energy],
+        output: ResourceType::UtriumBar,
+        station: Station::Factory,
+    },
+];
+
+fn recipe_for(output: ResourceType) -> Option<&'static Recipe> {
+    RECIPES.iter().find(|recipe| recipe.output == output)
+}
+
+// which structure(s) a chemist works - a lab reaction line (one reaction lab fed by two
+// reagent labs) or a factory producing commodities straight out of its own store.
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ChemistStation {
+    Lab {
+        #[serde(rename = "rl")]
+        reaction_lab: ObjectId<StructureLab>,
+        #[serde(rename = "il")]
+        input_labs: [ObjectId<StructureLab>; 2],
+    },
+    Factory {
+        #[serde(rename = "f")]
+        factory: ObjectId<StructureFactory>,
+    },
+}
+
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Chemist {
+    #[serde(rename = "r")]
+    pub home_room: RoomName,
+    // which compound this chemist's station is currently producing
+    #[serde(rename = "p")]
+    pub product: ResourceType,
+    #[serde(rename = "s")]
+    pub station: ChemistStation,
+}
+
+impl Worker for Chemist {
+    fn find_task(
+        &self,
+        pos: Position,
+        store: &Store,
+        _worker_roles: &HashSet<WorkerRole>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        _build_plan: &mut BuildPlan,
+        _decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
+    ) -> TaskQueueEntry {
+        match game::rooms().get(self.home_room) {
+            Some(room) => {
+                let carrying = store.get_used_capacity(None) > 0;
+                if let Some(task) =
+                    lifecycle::decide(&room, pos, ticks_to_live, body_cost, carrying)
+                {
+                    return TaskQueueEntry::new_unreserved(task);
+                }
+                find_chemistry_task(&room, self, store, task_reservations, owner)
+            }
+            None => {
+                warn!("couldn't see room for task find, must be an orphan");
+                TaskQueueEntry::new_unreserved(Task::IdleUntil(u32::MAX))
+            }
+        }
+    }
+
+    fn get_body_for_creep(&self, _spawn: &StructureSpawn) -> Vec<Part> {
+        use Part::*;
+        // chemists only ever move resources between labs/factory/storage, no WORK needed
+        vec![Move, Carry, Carry, Move]
+    }
+}
+
+fn find_chemistry_task(
+    room: &Room,
+    chemist: &Chemist,
+    store: &Store,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
+) -> TaskQueueEntry {
+    let recipe = match recipe_for(chemist.product) {
+        Some(recipe) => recipe,
+        None => {
+            warn!("no recipe known for product {:?}", chemist.product);
+            return TaskQueueEntry::new_unreserved(Task::IdleUntil(u32::MAX));
+        }
+    };
+
+    // already carrying something - either one of the reagents we're ferrying in, or
+    // finished product we're ferrying out to storage
+    let carried_amount = store.get_used_capacity(None);
+    if carried_amount > 0 {
+        for resource_type in recipe.inputs {
+            if store.get_used_capacity(Some(resource_type)) > 0 {
+                match chemist.station {
+                    ChemistStation::Lab { input_labs, .. } => {
+                        if let Some((lab_id, needed)) =
+                            lab_needing(&input_labs, recipe, resource_type)
+                        {
+                            let task = Task::LoadLab(lab_id, resource_type);
+                            if task_reservations.remaining_capacity(&task, needed, 0) > 0 {
+                                return TaskQueueEntry::new(
+                                    task,
+                                    carried_amount,
+                                    owner,
+                                    task_reservations,
+                                );
+                            }
+                        }
+                    }
+                    ChemistStation::Factory { factory } => {
+                        if let Some((target, needed)) = factory_needing(factory, resource_type) {
+                            let task = Task::DeliverToStructure(target, resource_type);
+                            if task_reservations.remaining_capacity(&task, needed, 0) > 0 {
+                                return TaskQueueEntry::new(
+                                    task,
+                                    carried_amount,
+                                    owner,
+                                    task_reservations,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if store.get_used_capacity(Some(recipe.output)) > 0 {
+            if let Some(storage) = room.storage() {
+                let task = Task::DeliverToStructure(storage.id().into_type(), recipe.output);
+                return TaskQueueEntry::new(task, carried_amount, owner, task_reservations);
+            }
+        }
+    }
+
+    // reagents loaded and off cooldown - trigger the reaction/production
+    match chemist.station {
+        ChemistStation::Lab {
+            reaction_lab,
+            input_labs,
+        } => {
+            if let (Some(lab), Ok(labs)) = (
+                reaction_lab.resolve(),
+                input_labs
+                    .iter()
+                    .map(|id| id.resolve().ok_or(()))
+                    .collect::<Result<Vec<_>, ()>>(),
+            ) {
+                let ready = labs
+                    .iter()
+                    .enumerate()
+                    .all(|(i, lab)| lab.store().get_used_capacity(Some(recipe.inputs[i])) > 0);
+                if ready && lab.cooldown() == 0 {
+                    let task = Task::RunReaction(reaction_lab, input_labs);
+                    if task_reservations.reserved(&task) == 0 {
+                        return TaskQueueEntry::new(task, 1, owner, task_reservations);
+                    }
+                }
+            }
+        }
+        ChemistStation::Factory { factory } => {
+            if let Some(factory_obj) = factory.resolve() {
+                let ready = recipe
+                    .inputs
+                    .iter()
+                    .all(|resource_type| {
+                        factory_obj.store().get_used_capacity(Some(*resource_type)) > 0
+                    });
+                if ready && factory_obj.cooldown() == 0 {
+                    let task = Task::ProduceCommodity(factory, recipe.output);
+                    if task_reservations.reserved(&task) == 0 {
+                        return TaskQueueEntry::new(task, 1, owner, task_reservations);
+                    }
+                }
+            }
+        }
+    }
+
+    // fetch a reagent that's low from storage/terminal
+    for resource_type in recipe.inputs {
+        let needed = match chemist.station {
+            ChemistStation::Lab { input_labs, .. } => {
+                lab_needing(&input_labs, recipe, resource_type).map(|(_, needed)| needed)
+            }
+            ChemistStation::Factory { factory } => {
+                factory_needing(factory, resource_type).map(|(_, needed)| needed)
+            }
+        };
+        if let Some(needed) = needed {
+            // cap to what this chemist can actually carry - `needed` so far is only the
+            // target lab/factory's free capacity, which is routinely bigger than a chemist's
+            // small carry-only body, and over-reserving past that starves other workers out
+            // of the rest of the task
+            let free_capacity: u32 = store.get_free_capacity(Some(resource_type)).try_into().unwrap_or(0);
+            let reserve_amount = std::cmp::min(needed, free_capacity);
+            if reserve_amount == 0 {
+                continue;
+            }
+            if let Some(source) = find_reagent_source(room, resource_type) {
+                let task = Task::TakeFromStructure(source, resource_type);
+                if task_reservations.remaining_capacity(&task, needed, 0) >= reserve_amount {
+                    return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+                }
+            }
+        }
+    }
+
+    // product ready at the producing structure - haul it to storage
+    let product_ready = match chemist.station {
+        ChemistStation::Lab { reaction_lab, .. } => reaction_lab.resolve().map(|lab| {
+            (
+                reaction_lab.into_type::<Structure>(),
+                lab.store().get_used_capacity(Some(recipe.output)),
+            )
+        }),
+        ChemistStation::Factory { factory } => factory.resolve().map(|factory_obj| {
+            (
+                factory.into_type::<Structure>(),
+                factory_obj.store().get_used_capacity(Some(recipe.output)),
+            )
+        }),
+    };
+    if let Some((id, product_amount)) = product_ready {
+        if product_amount > 0 {
+            let task = Task::TakeFromStructure(id, recipe.output);
+            return TaskQueueEntry::new(task, product_amount, owner, task_reservations);
+        }
+    }
+
+    TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
+}
+
+// returns the input lab that still wants more of `resource_type`, plus how much more it
+// can hold, if one of the recipe's two reagent labs is under-stocked
+fn lab_needing(
+    input_labs: &[ObjectId<StructureLab>; 2],
+    recipe: &Recipe,
+    resource_type: ResourceType,
+) -> Option<(ObjectId<StructureLab>, u32)> {
+    let index = recipe.inputs.iter().position(|r| *r == resource_type)?;
+    let lab_id = input_labs[index];
+    let lab = lab_id.resolve()?;
+    let needed = lab
+        .store()
+        .get_free_capacity(Some(resource_type))
+        .try_into()
+        .unwrap_or(0);
+    if needed > 0 {
+        Some((lab_id, needed))
+    } else {
+        None
+    }
+}
+
+// returns the factory's own store as a delivery target for `resource_type`, plus how much
+// more it can hold, if it's under-stocked on that reagent
+fn factory_needing(
+    factory: ObjectId<StructureFactory>,
+    resource_type: ResourceType,
+) -> Option<(ObjectId<Structure>, u32)> {
+    let factory_obj = factory.resolve()?;
+    let needed = factory_obj
+        .store()
+        .get_free_capacity(Some(resource_type))
+        .try_into()
+        .unwrap_or(0);
+    if needed > 0 {
+        Some((factory.into_type(), needed))
+    } else {
+        None
+    }
+}
+
+fn find_reagent_source(room: &Room, resource_type: ResourceType) -> Option<ObjectId<Structure>> {
+    room.find(find::STRUCTURES, None).into_iter().find_map(|structure| {
+        let store = match &structure {
+            StructureObject::StructureStorage(o) => o.store(),
+            StructureObject::StructureTerminal(o) => o.store(),
+            _ => return None,
+        };
+        if store.get_used_capacity(Some(resource_type)) > 0 {
+            Some(structure.as_structure().id())
+        } else {
+            None
+        }
+    })
+}
@@ -1,21 +1,23 @@
 use log::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use screeps::{
-    constants::{find, Part, ResourceType},
+    constants::{find, Part, ResourceType, CARRY_CAPACITY, ENERGY_REGEN_TIME, MAX_CREEP_SIZE},
     enums::StructureObject,
-    local::RoomName,
+    local::{ObjectId, Position, RoomName},
     objects::{Room, Store, Structure, StructureSpawn},
     prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
     constants::*,
     game,
+    reservation::ReservationLedger,
     role::WorkerRole,
-    task::{Task, TaskQueueEntry},
-    worker::Worker,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -29,24 +31,32 @@ pub struct Hauler {
 impl Worker for Hauler {
     fn find_task(
         &self,
+        pos: Position,
         store: &Store,
         _worker_roles: &HashSet<WorkerRole>,
-        task_reservations: &mut HashMap<Task, u32>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        _build_plan: &mut BuildPlan,
+        _decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
     ) -> TaskQueueEntry {
         match game::rooms().get(self.home_room) {
             Some(room) => {
-                let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
-                if energy_amount > 0 {
-                    find_delivery_target(&room, energy_amount, task_reservations)
+                let carried_amount = store.get_used_capacity(None);
+                if let Some(task) =
+                    lifecycle::decide(&room, pos, ticks_to_live, body_cost, carried_amount > 0)
+                {
+                    return TaskQueueEntry::new_unreserved(task);
+                }
+                if carried_amount > 0 {
+                    find_delivery_target(&room, store, task_reservations, owner)
                 } else {
-                    let energy_capacity = store
-                        .get_free_capacity(Some(ResourceType::Energy))
-                        .try_into()
-                        .unwrap_or(0);
-                    if energy_capacity > 0 {
-                        find_energy(&room, energy_capacity, task_reservations)
+                    let free_capacity = store.get_free_capacity(None).try_into().unwrap_or(0);
+                    if free_capacity > 0 {
+                        find_pickup(&room, free_capacity, task_reservations, owner)
                     } else {
-                        warn!("no energy capacity! hurt?");
+                        warn!("no carry capacity! hurt?");
                         TaskQueueEntry::new_unreserved(Task::IdleUntil(
                             game::time() + NO_TASK_IDLE_TICKS,
                         ))
@@ -61,58 +71,153 @@ impl Worker for Hauler {
     }
 
     fn get_body_for_creep(&self, spawn: &StructureSpawn) -> Vec<Part> {
-        // scale the creep to larger depending on how much capacity we have available
-        let max_energy_avail = spawn
-            .room()
-            .expect("spawn to have room")
-            .energy_capacity_available();
-        let multiplier = std::cmp::min(
-            max_energy_avail / HAULER_COST_PER_MULTIPLIER,
-            HAULER_MAX_MULTIPLIER,
+        let room = spawn.room().expect("spawn to have room");
+        let max_energy_avail = room.energy_capacity_available();
+
+        // total energy/tick this room's sources can sustain - the throughput a hauler
+        // fleet actually needs to keep up with, rather than a flat size multiplier
+        let source_rate: f32 = room
+            .find(find::SOURCES, None)
+            .iter()
+            .map(|source| source.energy_capacity())
+            .sum::<u32>() as f32
+            / ENERGY_REGEN_TIME as f32;
+
+        // round trip from the spawn to the farthest source and back, as a cheap stand-in
+        // for the path this hauler will actually walk
+        let round_trip_ticks = room
+            .find(find::SOURCES, None)
+            .into_iter()
+            .map(|source| spawn.pos().get_range_to(source.pos()) * 2)
+            .max()
+            .unwrap_or(HAULER_DEFAULT_ROUND_TRIP_TICKS);
+
+        let carry_parts_needed =
+            ((source_rate * round_trip_ticks as f32) / CARRY_CAPACITY as f32).ceil() as u32;
+
+        // cap to what the room can afford, paired two carries to one move (we expect
+        // haulers to run on roads)
+        let max_affordable_carry =
+            (max_energy_avail / HAULER_COST_PER_CARRY_PAIR) * 2;
+
+        // leave room for the move parts below too - a body over MAX_CREEP_SIZE is rejected
+        // by spawn_creep outright, which would starve the room of haulers entirely rather
+        // than just spawning a smaller one
+        let max_carry_for_body_limit = (MAX_CREEP_SIZE * 2) / 3;
+
+        let carry_parts = carry_parts_needed.clamp(
+            2,
+            max_affordable_carry.max(2).min(max_carry_for_body_limit),
         );
+        let move_parts = (carry_parts + 1) / 2;
 
-        [Part::Carry, Part::Carry, Part::Move].repeat(multiplier as usize)
+        let mut body = vec![Part::Carry; carry_parts as usize];
+        body.extend(vec![Part::Move; move_parts as usize]);
+        body
     }
 }
 
-fn find_energy(
+// pickup threshold for a resource type - energy moves in much bigger piles than anything
+// else, so it gets its own, higher thresholds
+fn pickup_threshold(resource_type: ResourceType, dropped: bool) -> u32 {
+    match (resource_type, dropped) {
+        (ResourceType::Energy, true) => HAULER_ENERGY_PICKUP_THRESHOLD,
+        (ResourceType::Energy, false) => HAULER_ENERGY_WITHDRAW_THRESHOLD,
+        (_, true) => HAULER_MINERAL_PICKUP_THRESHOLD,
+        (_, false) => HAULER_MINERAL_WITHDRAW_THRESHOLD,
+    }
+}
+
+fn find_pickup(
     room: &Room,
-    energy_capacity: u32,
-    task_reservations: &mut HashMap<Task, u32>,
+    free_capacity: u32,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
 ) -> TaskQueueEntry {
-    // check for energy on the ground of sufficient quantity to care about
+    // check for dropped resources of any type, sufficient quantity to care about
     for resource in room.find(find::DROPPED_RESOURCES, None) {
         let resource_amount = resource.amount();
-        if resource.resource_type() == ResourceType::Energy
-            && resource_amount >= HAULER_ENERGY_PICKUP_THRESHOLD
-        {
-            let reserve_amount = std::cmp::min(resource_amount, energy_capacity);
+        if resource_amount >= pickup_threshold(resource.resource_type(), true) {
+            let reserve_amount = std::cmp::min(resource_amount, free_capacity);
             let task = Task::TakeFromResource(resource.id());
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= resource_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, resource_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
 
-    // check structures - containers and terminals only, don't want
-    // to have these taking from spawns or extensions!
+    // check containers - don't want to have these taking from spawns or extensions!
+    // storage and terminal are handled separately below, since we need their object
+    // references (not just their stores) for the balancing pass
     for structure in room.find(find::STRUCTURES, None) {
         let store = match &structure {
             StructureObject::StructureContainer(o) => o.store(),
-            StructureObject::StructureStorage(o) => o.store(),
-            StructureObject::StructureTerminal(o) => o.store(),
             _ => {
                 // we don't want to look at this!
                 continue;
             }
         };
 
-        let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
-        if energy_amount >= HAULER_ENERGY_WITHDRAW_THRESHOLD {
-            let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
-            let task = Task::TakeFromStructure(structure.as_structure().id(), ResourceType::Energy);
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= energy_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+        for resource_type in store.store_types() {
+            let amount = store.get_used_capacity(Some(resource_type));
+            if amount >= pickup_threshold(resource_type, false) {
+                let reserve_amount = std::cmp::min(amount, free_capacity);
+                let task = Task::TakeFromStructure(structure.as_structure().id(), resource_type);
+                if task_reservations.remaining_capacity(&task, amount, 0) >= reserve_amount {
+                    return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+                }
+            }
+        }
+    }
+
+    if let Some(storage) = room.storage() {
+        for resource_type in storage.store().store_types() {
+            let amount = storage.store().get_used_capacity(Some(resource_type));
+            if amount >= pickup_threshold(resource_type, false) {
+                let reserve_amount = std::cmp::min(amount, free_capacity);
+                let task =
+                    Task::TakeFromStructure(storage.id().into_type::<Structure>(), resource_type);
+                if task_reservations.remaining_capacity(&task, amount, 0) >= reserve_amount {
+                    return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+                }
+            }
+        }
+    }
+
+    if let Some(terminal) = room.terminal() {
+        for resource_type in terminal.store().store_types() {
+            let amount = terminal.store().get_used_capacity(Some(resource_type));
+            if amount >= pickup_threshold(resource_type, false) {
+                let reserve_amount = std::cmp::min(amount, free_capacity);
+                let task = Task::TakeFromStructure(
+                    terminal.id().into_type::<Structure>(),
+                    resource_type,
+                );
+                if task_reservations.remaining_capacity(&task, amount, 0) >= reserve_amount {
+                    return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+                }
+            }
+        }
+    }
+
+    // terminal-balancing pass: top the terminal up from storage for anything it's short of,
+    // per TERMINAL_BALANCE_TARGETS, even if storage's stock of that resource is below the
+    // normal withdrawal threshold above - keeping the terminal stocked for trades is worth a
+    // smaller trip than we'd otherwise bother with
+    if let (Some(terminal), Some(storage)) = (room.terminal(), room.storage()) {
+        for &(resource_type, target) in TERMINAL_BALANCE_TARGETS {
+            if terminal.store().get_used_capacity(Some(resource_type)) >= target {
+                continue;
+            }
+            let available = storage.store().get_used_capacity(Some(resource_type));
+            if available == 0 {
+                continue;
+            }
+            let reserve_amount = std::cmp::min(available, free_capacity);
+            let task =
+                Task::TakeFromStructure(storage.id().into_type::<Structure>(), resource_type);
+            if task_reservations.remaining_capacity(&task, available, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
@@ -122,17 +227,19 @@ fn find_energy(
 
 fn find_delivery_target(
     room: &Room,
-    energy_amount: u32,
-    task_reservations: &mut HashMap<Task, u32>,
+    store: &Store,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
 ) -> TaskQueueEntry {
-    // check structures - we'll do a pass looking for high priority structures
-    // like spawns and extensions and towers before we check terminal and storage -
-    // but we'll store their references here as we come accoss them
+    // we'll do a pass looking for high priority structures like spawns, extensions, and
+    // towers (energy only) before considering terminal and storage - but we'll store their
+    // references here as we come across them
     let mut maybe_storage = None;
     let mut maybe_terminal = None;
 
+    let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
     for structure in room.find(find::STRUCTURES, None) {
-        let (store, structure) = match structure {
+        let (capacity_store, structure) = match structure {
             // for the three object types that are important to fill, snag their store then cast
             // them right back to StructureObject
             StructureObject::StructureSpawn(ref o) => (o.store(), structure),
@@ -154,7 +261,10 @@ fn find_delivery_target(
             }
         };
 
-        let energy_capacity = store
+        if energy_amount == 0 {
+            continue;
+        }
+        let energy_capacity = capacity_store
             .get_free_capacity(Some(ResourceType::Energy))
             .try_into()
             .unwrap_or(0);
@@ -163,51 +273,65 @@ fn find_delivery_target(
             let task =
                 Task::DeliverToStructure(structure.as_structure().id(), ResourceType::Energy);
             // if it's not already got enough energy on the way, take the job even if we'll overfill
-            if *task_reservations.get(&task).unwrap_or(&0) < energy_capacity {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, energy_capacity, 0) > 0 {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
 
-    // check the terminal if we found one
-    if let Some(terminal) = maybe_terminal {
-        let store = terminal.store();
-        if store.get_used_capacity(Some(ResourceType::Energy)) < TERMINAL_ENERGY_TARGET {
-            let energy_capacity = store
-                .get_free_capacity(Some(ResourceType::Energy))
+    // route every carried resource type toward the terminal/storage per
+    // TERMINAL_BALANCE_TARGETS - surplus (anything the terminal isn't yet holding its
+    // target amount of) is pushed in; whatever the terminal doesn't need falls back to storage
+    for resource_type in store.store_types() {
+        let carried_amount = store.get_used_capacity(Some(resource_type));
+        if carried_amount == 0 {
+            continue;
+        }
+        let target = TERMINAL_BALANCE_TARGETS
+            .iter()
+            .find(|(ty, _)| *ty == resource_type)
+            .map(|(_, amount)| *amount)
+            .unwrap_or(0);
+
+        if let Some(terminal) = &maybe_terminal {
+            let terminal_store = terminal.store();
+            if terminal_store.get_used_capacity(Some(resource_type)) < target {
+                let free_capacity = terminal_store
+                    .get_free_capacity(Some(resource_type))
+                    .try_into()
+                    .unwrap_or(0);
+                if free_capacity > 0 {
+                    let reserve_amount = std::cmp::min(carried_amount, free_capacity);
+                    let task = Task::DeliverToStructure(
+                        terminal.id().into_type::<Structure>(),
+                        resource_type,
+                    );
+                    if task_reservations.remaining_capacity(&task, free_capacity, 0) >= reserve_amount
+                    {
+                        return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+                    }
+                }
+            }
+        }
+
+        if let Some(storage) = &maybe_storage {
+            let storage_store = storage.store();
+            let free_capacity = storage_store
+                .get_free_capacity(Some(resource_type))
                 .try_into()
                 .unwrap_or(0);
-            if energy_capacity > 0 {
-                let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
+            if free_capacity > 0 {
+                let reserve_amount = std::cmp::min(carried_amount, free_capacity);
                 let task = Task::DeliverToStructure(
-                    terminal.id().into_type::<Structure>(),
-                    ResourceType::Energy,
+                    storage.id().into_type::<Structure>(),
+                    resource_type,
                 );
-                if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= energy_capacity {
-                    return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+                if task_reservations.remaining_capacity(&task, free_capacity, 0) >= reserve_amount {
+                    return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
                 }
             }
         }
     }
 
-    // and finally check the storage
-    if let Some(storage) = maybe_storage {
-        let store = storage.store();
-        let energy_capacity = store
-            .get_free_capacity(Some(ResourceType::Energy))
-            .try_into()
-            .unwrap_or(0);
-        if energy_capacity > 0 {
-            let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
-            let task = Task::DeliverToStructure(
-                storage.id().into_type::<Structure>(),
-                ResourceType::Energy,
-            );
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= energy_capacity {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
-            }
-        }
-    }
-
     TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
 }
@@ -4,15 +4,18 @@ use std::collections::HashSet;
 use screeps::{
     constants::look,
     constants::Part,
-    local::Position,
-    objects::{Store, StructureSpawn},
+    game,
+    local::{ObjectId, Position},
+    objects::{Store, Structure, StructureSpawn},
     prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
+    reservation::ReservationLedger,
     role::WorkerRole,
-    task::{Task, TaskQueueEntry},
-    worker::Worker,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -22,10 +25,37 @@ pub struct SourceHarvester {
 }
 
 impl Worker for SourceHarvester {
-    fn find_task(&self, _store: &Store, _worker_roles: &HashSet<WorkerRole>) -> TaskQueueEntry {
+    fn find_task(
+        &self,
+        pos: Position,
+        store: &Store,
+        _worker_roles: &HashSet<WorkerRole>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        _build_plan: &mut BuildPlan,
+        _decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
+    ) -> TaskQueueEntry {
+        // big bodies like this one are exactly the case worth paying to renew instead of
+        // recycling, so it's worth the trip even mid-harvest once we're empty
+        if let Some(room) = game::rooms().get(self.source_position.room_name()) {
+            let carrying_energy = store.get_used_capacity(None) > 0;
+            if let Some(task) =
+                lifecycle::decide(&room, pos, ticks_to_live, body_cost, carrying_energy)
+            {
+                return TaskQueueEntry::new_unreserved(task);
+            }
+        }
+
         match self.source_position.look_for(look::SOURCES) {
             Ok(sources) => match sources.first() {
-                Some(source) => TaskQueueEntry::new(Task::HarvestEnergyForever(source.id()), 1),
+                Some(source) => TaskQueueEntry::new(
+                    Task::HarvestEnergyForever(source.id()),
+                    1,
+                    owner,
+                    task_reservations,
+                ),
                 None => {
                     TaskQueueEntry::new_unreserved(Task::MoveToPosition(self.source_position, 1))
                 }
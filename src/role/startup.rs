@@ -6,17 +6,20 @@ use screeps::{
     constants::{find, Part, ResourceType},
     enums::StructureObject,
     game,
-    local::RoomName,
-    objects::{Room, Store, StructureSpawn},
+    local::{ObjectId, Position, RoomName},
+    objects::{Room, Store, Structure, StructureSpawn},
     prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
     constants::*,
+    dse::{self, Dse, RemainingWorkFraction, ReservationPressure},
     movement::MovementProfile,
+    reservation::ReservationLedger,
     role::WorkerRole,
-    task::{Task, TaskQueueEntry},
-    worker::Worker,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -28,21 +31,45 @@ pub struct Startup {
 }
 
 impl Worker for Startup {
-    fn find_task(&self, store: &Store, _worker_roles: &HashSet<WorkerRole>) -> TaskQueueEntry {
+    fn find_task(
+        &self,
+        pos: Position,
+        store: &Store,
+        _worker_roles: &HashSet<WorkerRole>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        build_plan: &mut BuildPlan,
+        decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
+    ) -> TaskQueueEntry {
         match game::rooms().get(self.home_room) {
             Some(room) => {
                 let energy_amount = store
                     .get_used_capacity(Some(ResourceType::Energy))
                     .try_into()
                     .unwrap_or(0);
+                if let Some(task) =
+                    lifecycle::decide(&room, pos, ticks_to_live, body_cost, energy_amount > 0)
+                {
+                    return TaskQueueEntry::new_unreserved(task);
+                }
                 if energy_amount > 0 {
-                    find_startup_task(&room, energy_amount)
+                    find_startup_task(
+                        &room,
+                        pos,
+                        energy_amount,
+                        task_reservations,
+                        build_plan,
+                        decayed_structures,
+                        owner,
+                    )
                 } else {
                     let energy_capacity = store
                         .get_free_capacity(Some(ResourceType::Energy))
                         .try_into()
                         .unwrap_or(0);
-                    find_energy_or_source(&room, energy_capacity)
+                    find_energy_or_source(&room, energy_capacity, task_reservations, owner)
                 }
             }
             None => {
@@ -62,8 +89,29 @@ impl Worker for Startup {
     }
 }
 
-fn find_startup_task(room: &Room, energy_amount: u32) -> TaskQueueEntry {
-    // look for supply tasks a spawn or extension
+// same Dse-driven approach as `Builder`'s work selection: enumerate every plausible
+// delivery/repair/build/upgrade candidate and score them together instead of returning
+// on the first match, so e.g. a dying wall can outscore a routine extension refill
+const STARTUP_DELIVER_BASE_WEIGHT: f32 = 1.25;
+const STARTUP_REPAIR_BASE_WEIGHT: f32 = 1.;
+const STARTUP_DECAY_REPAIR_BASE_WEIGHT: f32 = 0.4;
+const STARTUP_BUILD_BASE_WEIGHT: f32 = 0.75;
+const STARTUP_UPGRADE_BASE_WEIGHT: f32 = 0.25;
+const STARTUP_REPAIR_WATERMARK: u32 = 10_000;
+const STARTUP_MAX_RANGE: u32 = 50;
+
+fn find_startup_task(
+    room: &Room,
+    pos: Position,
+    energy_amount: u32,
+    task_reservations: &mut ReservationLedger,
+    build_plan: &mut BuildPlan,
+    decayed_structures: &[ObjectId<Structure>],
+    owner: WorkerId,
+) -> TaskQueueEntry {
+    let mut candidates = Vec::new();
+
+    // delivery candidates - a spawn or extension
     for structure in room.find(find::STRUCTURES, None) {
         let (store, structure) = match structure {
             // for the three object types that are important to fill, snag their store then cast
@@ -81,15 +129,22 @@ fn find_startup_task(room: &Room, energy_amount: u32) -> TaskQueueEntry {
             .try_into()
             .unwrap_or(0);
         if energy_capacity > 0 {
-            let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
-            return TaskQueueEntry::new(
-                Task::DeliverToStructure(structure.as_structure().id(), ResourceType::Energy),
-                reserve_amount,
-            );
+            let task =
+                Task::DeliverToStructure(structure.as_structure().id(), ResourceType::Energy);
+            candidates.push((
+                task,
+                Dse {
+                    base_weight: STARTUP_DELIVER_BASE_WEIGHT,
+                    considerations: vec![Box::new(dse::InverseDistance {
+                        range: pos.get_range_to(structure.pos()),
+                        max_range: STARTUP_MAX_RANGE,
+                    })],
+                },
+            ));
         }
     }
 
-    // look for repair tasks
+    // repair candidates
     // note that we're using STRUCTURES instead of MY_STRUCTURES
     // so we can catch roads, containers, and walls
     for structure_object in room.find(find::STRUCTURES, None) {
@@ -100,37 +155,93 @@ fn find_startup_task(room: &Room, energy_amount: u32) -> TaskQueueEntry {
         let hits_max = structure.hits_max();
 
         // if hits_max is 0, it's indestructable
-        if hits_max != 0 {
-            // if the hits are below our 'watermark' to repair to
-            // as well as less than half of this struture's max, repair!
-            if hits < 10_000 && hits * 2 < hits_max {
-                return TaskQueueEntry::new(Task::Repair(structure.id()), energy_amount);
-            }
+        if hits_max == 0 {
+            continue;
+        }
+        // if the hits are below our 'watermark' to repair to
+        // as well as less than half of this struture's max, it's a repair candidate
+        if hits < STARTUP_REPAIR_WATERMARK && hits * 2 < hits_max {
+            candidates.push((
+                Task::Repair(structure.id()),
+                Dse {
+                    base_weight: STARTUP_REPAIR_BASE_WEIGHT,
+                    considerations: vec![
+                        Box::new(dse::InverseDistance {
+                            range: pos.get_range_to(structure.pos()),
+                            max_range: STARTUP_MAX_RANGE,
+                        }),
+                        Box::new(dse::NearDestruction { hits, hits_max }),
+                    ],
+                },
+            ));
+        } else if decayed_structures.contains(&structure.id()) {
+            // above the reactive watermark, but the background decay scanner flagged it as
+            // worth topping off - low weight, so it only wins when nothing more urgent needs
+            // this startup creep's attention
+            candidates.push((
+                Task::Repair(structure.id()),
+                Dse {
+                    base_weight: STARTUP_DECAY_REPAIR_BASE_WEIGHT,
+                    considerations: vec![Box::new(dse::InverseDistance {
+                        range: pos.get_range_to(structure.pos()),
+                        max_range: STARTUP_MAX_RANGE,
+                    })],
+                },
+            ));
         }
     }
 
-    // look for construction tasks next
-    if let Some(construction_site) = room
-        .find(find::MY_CONSTRUCTION_SITES, None)
-        .into_iter()
-        .next()
-    {
-        // we can unwrap this id because we know the room the site is in must be visible
-        return TaskQueueEntry::new(
-            Task::Build(construction_site.try_id().unwrap()),
-            energy_amount,
-        );
+    // the single highest-priority pending construction site, per the dependency-ordered
+    // build plan, instead of scoring every raw `find::MY_CONSTRUCTION_SITES` result
+    if let Some(site_id) = build_plan.best_site(room, pos) {
+        if let Some(construction_site) = site_id.resolve() {
+            let progress = construction_site.progress();
+            let progress_total = construction_site.progress_total();
+            candidates.push((
+                Task::Build(site_id),
+                Dse {
+                    base_weight: STARTUP_BUILD_BASE_WEIGHT,
+                    considerations: vec![
+                        Box::new(dse::InverseDistance {
+                            range: pos.get_range_to(construction_site.pos()),
+                            max_range: STARTUP_MAX_RANGE,
+                        }),
+                        Box::new(RemainingWorkFraction {
+                            progress,
+                            progress_total,
+                        }),
+                    ],
+                },
+            ));
+        }
     }
 
-    // finally, upgrade
+    // upgrading the controller is always an option, but it's the least attractive one
     if let Some(controller) = room.controller() {
-        return TaskQueueEntry::new(Task::Upgrade(controller.id()), 1);
+        candidates.push((
+            Task::Upgrade(controller.id()),
+            Dse {
+                base_weight: STARTUP_UPGRADE_BASE_WEIGHT,
+                considerations: vec![Box::new(dse::InverseDistance {
+                    range: pos.get_range_to(controller.pos()),
+                    max_range: STARTUP_MAX_RANGE,
+                })],
+            },
+        ));
     }
 
-    TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
+    match dse::pick_best(candidates) {
+        Some(task) => TaskQueueEntry::new(task, energy_amount, owner, task_reservations),
+        None => TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS)),
+    }
 }
 
-fn find_energy_or_source(room: &Room, energy_capacity: u32) -> TaskQueueEntry {
+fn find_energy_or_source(
+    room: &Room,
+    energy_capacity: u32,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
+) -> TaskQueueEntry {
     // check for energy on the ground of sufficient quantity to care about
     for resource in room.find(find::DROPPED_RESOURCES, None) {
         let resource_amount = resource.amount();
@@ -138,7 +249,10 @@ fn find_energy_or_source(room: &Room, energy_capacity: u32) -> TaskQueueEntry {
             && resource_amount >= BUILDER_ENERGY_PICKUP_THRESHOLD
         {
             let reserve_amount = std::cmp::min(resource_amount, energy_capacity);
-            return TaskQueueEntry::new(Task::TakeFromResource(resource.id()), reserve_amount);
+            let task = Task::TakeFromResource(resource.id());
+            if task_reservations.remaining_capacity(&task, resource_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+            }
         }
     }
 
@@ -158,16 +272,19 @@ fn find_energy_or_source(room: &Room, energy_capacity: u32) -> TaskQueueEntry {
         let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
         if energy_amount >= BUILDER_ENERGY_WITHDRAW_THRESHOLD {
             let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
-            return TaskQueueEntry::new(
-                Task::TakeFromStructure(structure.as_structure().id(), ResourceType::Energy),
-                reserve_amount,
-            );
+            let task = Task::TakeFromStructure(structure.as_structure().id(), ResourceType::Energy);
+            if task_reservations.remaining_capacity(&task, energy_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
+            }
         }
     }
 
     // look for sources with energy we can harvest as a last resort
     if let Some(source) = room.find(find::SOURCES_ACTIVE, None).into_iter().next() {
-        return TaskQueueEntry::new(Task::HarvestEnergyUntilFull(source.id()), 1);
+        let task = Task::HarvestEnergyUntilFull(source.id());
+        if task_reservations.reserved(&task) == 0 {
+            return TaskQueueEntry::new(task, 1, owner, task_reservations);
+        }
     }
 
     TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
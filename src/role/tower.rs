@@ -1,16 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use screeps::{
-    constants::Part,
-    local::RoomName,
-    objects::{Store, StructureSpawn},
+    constants::{find, Part},
+    game,
+    local::{ObjectId, Position, RoomName},
+    objects::{Room, Store, Structure, StructureSpawn},
+    prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
+    constants::*,
+    reservation::ReservationLedger,
     role::WorkerRole,
     task::{Task, TaskQueueEntry},
-    worker::Worker,
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -21,11 +26,20 @@ pub struct Tower {
 impl Worker for Tower {
     fn find_task(
         &self,
-        _store: &Store,
+        _pos: Position,
+        store: &Store,
         _worker_roles: &HashSet<WorkerRole>,
-        _task_reservations: &mut HashMap<Task, u32>,
+        _task_reservations: &mut ReservationLedger,
+        _ticks_to_live: u32,
+        _body_cost: u32,
+        _build_plan: &mut BuildPlan,
+        _decayed_structures: &[ObjectId<Structure>],
+        _owner: WorkerId,
     ) -> TaskQueueEntry {
-        unimplemented!()
+        match game::rooms().get(self.room) {
+            Some(room) => find_tower_task(&room, store),
+            None => TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS)),
+        }
     }
 
     fn get_body_for_creep(&self, _spawn: &StructureSpawn) -> Vec<Part> {
@@ -36,3 +50,36 @@ impl Worker for Tower {
         false
     }
 }
+
+// priority ladder a tower re-evaluates from scratch every tick: finish off the weakest
+// hostile first, then patch up the most hurt friendly, and only spend down energy on
+// structure repair once there's enough left in reserve to still fight back if attacked.
+fn find_tower_task(room: &Room, store: &Store) -> TaskQueueEntry {
+    let hostiles = room.find(find::HOSTILE_CREEPS, None);
+    if let Some(target) = hostiles.into_iter().min_by_key(|creep| creep.hits()) {
+        return TaskQueueEntry::new_unreserved(Task::AttackWithTower(target.id()));
+    }
+
+    let damaged_creeps = room.find(find::MY_CREEPS, None);
+    if let Some(target) = damaged_creeps
+        .into_iter()
+        .filter(|creep| creep.hits() < creep.hits_max())
+        .min_by_key(|creep| creep.hits())
+    {
+        return TaskQueueEntry::new_unreserved(Task::HealWithTower(target.id()));
+    }
+
+    if store.get_used_capacity(None) >= TOWER_REPAIR_ENERGY_FLOOR {
+        let damaged_structures = room.find(find::MY_STRUCTURES, None);
+        if let Some(target) = damaged_structures
+            .into_iter()
+            .map(|structure| structure.as_structure())
+            .filter(|structure| structure.hits() < structure.hits_max())
+            .min_by_key(|structure| structure.hits())
+        {
+            return TaskQueueEntry::new_unreserved(Task::RepairWithTower(target.id()));
+        }
+    }
+
+    TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
+}
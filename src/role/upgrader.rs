@@ -1,21 +1,23 @@
 use log::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use screeps::{
     constants::{find, Direction, Part, ResourceType, Terrain},
     enums::StructureObject,
     game,
-    local::RoomName,
-    objects::{Room, Store, StructureSpawn},
+    local::{ObjectId, Position, RoomName},
+    objects::{Room, Store, Structure, StructureSpawn},
     prelude::*,
 };
 
 use crate::{
+    build_plan::BuildPlan,
     constants::*,
+    reservation::ReservationLedger,
     role::WorkerRole,
-    task::{Task, TaskQueueEntry},
-    worker::Worker,
+    task::{lifecycle, Task, TaskQueueEntry},
+    worker::{Worker, WorkerId},
 };
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -29,21 +31,33 @@ pub struct Upgrader {
 impl Worker for Upgrader {
     fn find_task(
         &self,
+        pos: Position,
         store: &Store,
         _worker_roles: &HashSet<WorkerRole>,
-        task_reservations: &mut HashMap<Task, u32>,
+        task_reservations: &mut ReservationLedger,
+        ticks_to_live: u32,
+        body_cost: u32,
+        _build_plan: &mut BuildPlan,
+        _decayed_structures: &[ObjectId<Structure>],
+        owner: WorkerId,
     ) -> TaskQueueEntry {
         match game::rooms().get(self.home_room) {
             Some(room) => {
-                if store.get_used_capacity(Some(ResourceType::Energy)) > 0 {
-                    find_upgrade_task(&room, task_reservations)
+                let energy_amount = store.get_used_capacity(Some(ResourceType::Energy));
+                if let Some(task) =
+                    lifecycle::decide(&room, pos, ticks_to_live, body_cost, energy_amount > 0)
+                {
+                    return TaskQueueEntry::new_unreserved(task);
+                }
+                if energy_amount > 0 {
+                    find_upgrade_task(&room, owner, task_reservations)
                 } else {
                     let energy_capacity = store
                         .get_free_capacity(Some(ResourceType::Energy))
                         .try_into()
                         .unwrap_or(0);
                     if energy_capacity > 0 {
-                        find_energy_or_source(&room, energy_capacity, task_reservations)
+                        find_energy_or_source(&room, energy_capacity, task_reservations, owner)
                     } else {
                         warn!("no energy capacity!");
                         TaskQueueEntry::new_unreserved(Task::IdleUntil(u32::MAX))
@@ -63,9 +77,13 @@ impl Worker for Upgrader {
     }
 }
 
-fn find_upgrade_task(room: &Room, task_reservations: &mut HashMap<Task, u32>) -> TaskQueueEntry {
+fn find_upgrade_task(
+    room: &Room,
+    owner: WorkerId,
+    task_reservations: &mut ReservationLedger,
+) -> TaskQueueEntry {
     if let Some(controller) = room.controller() {
-        TaskQueueEntry::new(Task::Upgrade(controller.id()), 1, task_reservations)
+        TaskQueueEntry::new(Task::Upgrade(controller.id()), 1, owner, task_reservations)
     } else {
         TaskQueueEntry::new_unreserved(Task::IdleUntil(game::time() + NO_TASK_IDLE_TICKS))
     }
@@ -74,7 +92,8 @@ fn find_upgrade_task(room: &Room, task_reservations: &mut HashMap<Task, u32>) ->
 fn find_energy_or_source(
     room: &Room,
     energy_capacity: u32,
-    task_reservations: &mut HashMap<Task, u32>,
+    task_reservations: &mut ReservationLedger,
+    owner: WorkerId,
 ) -> TaskQueueEntry {
     // check for energy on the ground of sufficient quantity to care about
     for resource in room.find(find::DROPPED_RESOURCES, None) {
@@ -84,8 +103,8 @@ fn find_energy_or_source(
         {
             let reserve_amount = std::cmp::min(resource_amount, energy_capacity);
             let task = Task::TakeFromResource(resource.id());
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= resource_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, resource_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
@@ -107,8 +126,8 @@ fn find_energy_or_source(
         if energy_amount >= UPGRADER_ENERGY_WITHDRAW_THRESHOLD {
             let reserve_amount = std::cmp::min(energy_amount, energy_capacity);
             let task = Task::TakeFromStructure(structure.as_structure().id(), ResourceType::Energy);
-            if *task_reservations.get(&task).unwrap_or(&0) + reserve_amount <= energy_amount {
-                return TaskQueueEntry::new(task, reserve_amount, task_reservations);
+            if task_reservations.remaining_capacity(&task, energy_amount, 0) >= reserve_amount {
+                return TaskQueueEntry::new(task, reserve_amount, owner, task_reservations);
             }
         }
     }
@@ -126,8 +145,8 @@ fn find_energy_or_source(
             }
         }
         let task = Task::HarvestEnergyUntilFull(source.id());
-        if *task_reservations.get(&task).unwrap_or(&0) < harvest_positions {
-            return TaskQueueEntry::new(task, 1, task_reservations);
+        if task_reservations.reserved(&task) < harvest_positions {
+            return TaskQueueEntry::new(task, 1, owner, task_reservations);
         }
     }
 
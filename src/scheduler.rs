@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use log::*;
+use screeps::game;
+
+use crate::constants::*;
+
+// a periodic job a role or subsystem wants run no more often than `interval` ticks - a
+// single place that decides when expensive shard-wide scans (currently
+// `scan_and_register_structures`) are actually allowed to run. roles' `find_task` don't
+// register entries here; they still idle with `Task::IdleUntil(game::time() + N)`.
+pub struct ScheduledEntry {
+    pub interval: u32,
+    pub last_run: u32,
+    pub priority: u8,
+}
+
+impl ScheduledEntry {
+    fn is_due(&self, tick: u32) -> bool {
+        tick.saturating_sub(self.last_run) >= self.interval
+    }
+}
+
+// drains due entries in priority order (highest first) each tick, stopping once the
+// accumulated CPU spent this tick crosses `SCHEDULER_CPU_BUDGET` so a burst of due scans
+// can't blow the whole tick's CPU. anything left over simply stays due and gets picked up
+// next tick.
+#[derive(Default)]
+pub struct Scheduler {
+    entries: HashMap<&'static str, ScheduledEntry>,
+}
+
+impl Scheduler {
+    // registers a job under `name` if it isn't already tracked. calling this every tick
+    // with the same name is cheap and idiomatic - only the first registration sticks.
+    pub fn register(&mut self, name: &'static str, interval: u32, priority: u8) {
+        self.entries.entry(name).or_insert(ScheduledEntry {
+            interval,
+            last_run: 0,
+            priority,
+        });
+    }
+
+    // whether a registered job is due to run this tick. useful when the caller can't hand
+    // its job to `run_due` as a self-contained closure (e.g. it needs a `&mut` borrow the
+    // scheduler itself is already holding) and has to run it inline instead.
+    pub fn is_due(&self, name: &'static str) -> bool {
+        self.entries
+            .get(name)
+            .is_some_and(|entry| entry.is_due(game::time()))
+    }
+
+    // records that `name` ran this tick, for callers driving it via `is_due` instead of
+    // `run_due`.
+    pub fn mark_run(&mut self, name: &'static str) {
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.last_run = game::time();
+        }
+    }
+
+    // runs every due job whose name is in `jobs`, highest priority first, until either the
+    // list is exhausted or the tick's CPU budget runs out. `jobs` maps a registered name to
+    // the closure that actually performs the scan.
+    pub fn run_due(&mut self, jobs: &mut HashMap<&'static str, Box<dyn FnMut()>>) {
+        let tick = game::time();
+        let mut due: Vec<&'static str> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.is_due(tick))
+            .map(|(name, _)| *name)
+            .collect();
+        due.sort_by_key(|name| std::cmp::Reverse(self.entries[name].priority));
+
+        for name in due {
+            if game::cpu::get_used() >= SCHEDULER_CPU_BUDGET {
+                debug!("scheduler: CPU budget exhausted, deferring remaining jobs to next tick");
+                break;
+            }
+            if let Some(job) = jobs.get_mut(name) {
+                job();
+                if let Some(entry) = self.entries.get_mut(name) {
+                    entry.last_run = tick;
+                }
+            } else {
+                warn!("scheduler: job '{}' is due but wasn't supplied to run_due", name);
+            }
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::{hash_map, HashMap};
-
 use serde::{Deserialize, Serialize};
 
 use screeps::{
@@ -11,15 +9,19 @@ use screeps::{
 
 use crate::{
     movement::{MovementGoal, MovementProfile},
+    reservation::ReservationLedger,
     role::WorkerRole,
-    worker::WorkerReference,
+    worker::{WorkerId, WorkerReference},
 };
 
 mod build;
+mod chemistry;
 mod harvest;
+pub(crate) mod lifecycle;
 mod logistics;
 mod repair;
 mod spawn;
+mod tower;
 mod upgrade;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -50,6 +52,17 @@ pub enum Task {
     TakeFromResource(ObjectId<Resource>),
     TakeFromStructure(ObjectId<Structure>, ResourceType),
     DeliverToStructure(ObjectId<Structure>, ResourceType),
+    // lab/factory reservation
+    LoadLab(ObjectId<StructureLab>, ResourceType),
+    RunReaction(ObjectId<StructureLab>, [ObjectId<StructureLab>; 2]),
+    ProduceCommodity(ObjectId<StructureFactory>, ResourceType),
+    // no reservation - a creep decides this for itself based on its own ticks_to_live
+    RenewAtSpawn(ObjectId<StructureSpawn>),
+    RecycleAtSpawn(ObjectId<StructureSpawn>),
+    // no reservation - towers act alone and re-evaluate their priority ladder every tick
+    AttackWithTower(ObjectId<Creep>),
+    HealWithTower(ObjectId<Creep>),
+    RepairWithTower(ObjectId<Structure>),
 }
 
 #[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -71,31 +84,18 @@ impl TaskQueueEntry {
     pub fn new(
         task: Task,
         reservation_amount: u32,
-        task_reservations: &mut HashMap<Task, u32>,
+        owner: WorkerId,
+        task_reservations: &mut ReservationLedger,
     ) -> TaskQueueEntry {
-        if reservation_amount > 0 {
-            task_reservations
-                .entry(task)
-                .and_modify(|r| *r = r.saturating_add(reservation_amount))
-                .or_insert(reservation_amount);
-        }
+        task_reservations.reserve(task, reservation_amount, owner);
         TaskQueueEntry {
             task,
             reservation_amount,
         }
     }
 
-    pub fn remove_reservation(&self, task_reservations: &mut HashMap<Task, u32>) {
-        if self.reservation_amount > 0 {
-            if let hash_map::Entry::Occupied(mut o) = task_reservations.entry(self.task) {
-                // move the above modify logic into here so we dont hash twice
-                *o.get_mut() = o.get().saturating_sub(self.reservation_amount);
-
-                if *o.get() == 0 {
-                    o.remove();
-                }
-            }
-        }
+    pub fn remove_reservation(&self, owner: WorkerId, task_reservations: &mut ReservationLedger) {
+        task_reservations.release(self.task, self.reservation_amount, owner);
     }
 
     pub fn run_task(
@@ -143,8 +143,20 @@ impl TaskQueueEntry {
             Task::DeliverToStructure(id, ty) => {
                 logistics::deliver_to_structure(worker, &id, ty, movement_profile)
             }
+            Task::LoadLab(id, ty) => chemistry::load_lab(worker, &id, ty, movement_profile),
+            Task::RunReaction(id, input_labs) => {
+                chemistry::run_reaction(worker, &id, &input_labs, movement_profile)
+            }
+            Task::ProduceCommodity(id, resource_type) => {
+                chemistry::produce_commodity(worker, &id, resource_type, movement_profile)
+            }
             Task::SpawnCreep(role) => spawn::spawn_creep(worker, &role),
             Task::WaitToSpawn => spawn::wait_to_spawn(worker),
+            Task::RenewAtSpawn(id) => lifecycle::renew_at_spawn(worker, &id, movement_profile),
+            Task::RecycleAtSpawn(id) => lifecycle::recycle_at_spawn(worker, &id, movement_profile),
+            Task::AttackWithTower(id) => tower::attack_with_tower(worker, &id),
+            Task::HealWithTower(id) => tower::heal_with_tower(worker, &id),
+            Task::RepairWithTower(id) => tower::repair_with_tower(worker, &id),
         }
     }
 }
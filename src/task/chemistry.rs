@@ -0,0 +1,94 @@
+use screeps::{
+    constants::ResourceType,
+    local::ObjectId,
+    objects::{StructureFactory, StructureLab},
+    prelude::*,
+};
+
+use crate::{
+    movement::{MovementGoal, MovementProfile},
+    task::TaskResult,
+    worker::WorkerReference,
+};
+
+// carry a reagent to one of the reagent (input) labs. completes after a single transfer
+// attempt once adjacent to the lab, since `Chemist::find_task` only assigns this when the
+// lab still needs topping up and re-checks it on the next pass.
+pub fn load_lab(
+    worker: &WorkerReference,
+    id: &ObjectId<StructureLab>,
+    resource_type: ResourceType,
+    movement_profile: MovementProfile,
+) -> TaskResult {
+    match id.resolve() {
+        Some(lab) => {
+            if worker.pos().is_near_to(lab.pos()) {
+                if let Some(creep) = worker.as_creep() {
+                    let _ = creep.transfer(&lab, resource_type, None);
+                }
+                TaskResult::Complete
+            } else {
+                TaskResult::MoveMeTo(MovementGoal {
+                    pos: lab.pos(),
+                    range: 1,
+                    profile: movement_profile,
+                    avoid_creeps: false,
+                })
+            }
+        }
+        None => TaskResult::Complete,
+    }
+}
+
+// trigger a reaction in a lab whose reagent labs are already loaded. `Chemist::find_task` is
+// responsible for not assigning it again while the lab is still on cooldown - this only ever
+// walks the assigned creep to the reaction lab and runs the intent once it's adjacent to it.
+pub fn run_reaction(
+    worker: &WorkerReference,
+    id: &ObjectId<StructureLab>,
+    input_labs: &[ObjectId<StructureLab>; 2],
+    movement_profile: MovementProfile,
+) -> TaskResult {
+    match (id.resolve(), input_labs[0].resolve(), input_labs[1].resolve()) {
+        (Some(lab), Some(lab1), Some(lab2)) => {
+            if worker.pos().is_near_to(lab.pos()) {
+                let _ = lab.run_reaction(&lab1, &lab2);
+                TaskResult::Complete
+            } else {
+                TaskResult::MoveMeTo(MovementGoal {
+                    pos: lab.pos(),
+                    range: 1,
+                    profile: movement_profile,
+                    avoid_creeps: false,
+                })
+            }
+        }
+        _ => TaskResult::Complete,
+    }
+}
+
+// trigger commodity production in a factory whose reagents are already loaded, leaving the
+// reagent-loading itself to whichever creep `Chemist::find_task` assigned that job to.
+pub fn produce_commodity(
+    worker: &WorkerReference,
+    id: &ObjectId<StructureFactory>,
+    resource_type: ResourceType,
+    movement_profile: MovementProfile,
+) -> TaskResult {
+    match id.resolve() {
+        Some(factory) => {
+            if worker.pos().is_near_to(factory.pos()) {
+                let _ = factory.produce(resource_type);
+                TaskResult::Complete
+            } else {
+                TaskResult::MoveMeTo(MovementGoal {
+                    pos: factory.pos(),
+                    range: 1,
+                    profile: movement_profile,
+                    avoid_creeps: false,
+                })
+            }
+        }
+        None => TaskResult::Complete,
+    }
+}
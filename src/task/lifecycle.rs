@@ -0,0 +1,111 @@
+use screeps::{
+    constants::find,
+    local::{ObjectId, Position},
+    objects::{Room, StructureSpawn},
+    prelude::*,
+};
+
+use crate::{
+    constants::*,
+    movement::{MovementGoal, MovementProfile},
+    task::{Task, TaskResult},
+    worker::WorkerReference,
+};
+
+// checked by every creep role at the top of `find_task`: once a creep is far enough into
+// its decaying lifetime, this overrides normal task selection with a trip to the nearest
+// spawn to either renew (bodies expensive enough to be worth the energy) or recycle (cheap
+// ones, for the partial spawn-cost refund) instead. hysteresis between this threshold and
+// the one `renew_at_spawn` completes at keeps a creep hovering right at the line from
+// flapping between `RenewAtSpawn` and its normal work every other tick.
+pub fn decide(
+    room: &Room,
+    pos: Position,
+    ticks_to_live: u32,
+    body_cost: u32,
+    carrying_energy: bool,
+) -> Option<Task> {
+    if ticks_to_live >= LIFECYCLE_TTL_THRESHOLD {
+        return None;
+    }
+
+    let spawn = room
+        .find(find::MY_SPAWNS, None)
+        .into_iter()
+        .min_by_key(|spawn| pos.get_range_to(spawn.pos()))?;
+
+    // don't strand carried energy on the ground chasing a renew - wait until we're already
+    // empty, or already standing next to the spawn we'd be walking to anyway
+    if carrying_energy && !pos.is_near_to(spawn.pos()) {
+        return None;
+    }
+
+    if body_cost >= LIFECYCLE_RENEW_WORTHWHILE_BODY_COST {
+        Some(Task::RenewAtSpawn(spawn.id()))
+    } else {
+        Some(Task::RecycleAtSpawn(spawn.id()))
+    }
+}
+
+// walk to the spawn and renew until ticks_to_live climbs back out of the danger zone, with
+// enough hysteresis over `decide`'s threshold that we don't immediately hand the creep back
+// to `find_task` only for it to re-decide the same renew trip next tick.
+pub fn renew_at_spawn(
+    worker: &WorkerReference,
+    id: &ObjectId<StructureSpawn>,
+    movement_profile: MovementProfile,
+) -> TaskResult {
+    let ticks_to_live = worker
+        .as_creep()
+        .and_then(|creep| creep.ticks_to_live())
+        .unwrap_or(u32::MAX);
+    if ticks_to_live >= LIFECYCLE_TTL_THRESHOLD + LIFECYCLE_TTL_RENEWED_HYSTERESIS {
+        return TaskResult::Complete;
+    }
+
+    match id.resolve() {
+        Some(spawn) => {
+            if worker.pos().is_near_to(spawn.pos()) {
+                if let Some(creep) = worker.as_creep() {
+                    let _ = spawn.renew_creep(creep);
+                }
+                TaskResult::StillWorking
+            } else {
+                TaskResult::MoveMeTo(MovementGoal {
+                    pos: spawn.pos(),
+                    range: 1,
+                    profile: movement_profile,
+                    avoid_creeps: false,
+                })
+            }
+        }
+        None => TaskResult::Complete,
+    }
+}
+
+// walk to the spawn and recycle - this one always ends in `DestroyWorker` since there's no
+// coming back from it.
+pub fn recycle_at_spawn(
+    worker: &WorkerReference,
+    id: &ObjectId<StructureSpawn>,
+    movement_profile: MovementProfile,
+) -> TaskResult {
+    match id.resolve() {
+        Some(spawn) => {
+            if worker.pos().is_near_to(spawn.pos()) {
+                if let Some(creep) = worker.as_creep() {
+                    let _ = spawn.recycle_creep(creep);
+                }
+                TaskResult::DestroyWorker
+            } else {
+                TaskResult::MoveMeTo(MovementGoal {
+                    pos: spawn.pos(),
+                    range: 1,
+                    profile: movement_profile,
+                    avoid_creeps: false,
+                })
+            }
+        }
+        None => TaskResult::Complete,
+    }
+}
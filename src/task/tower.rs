@@ -0,0 +1,31 @@
+use screeps::{
+    local::ObjectId,
+    objects::{Creep, Structure},
+    prelude::*,
+};
+
+use crate::{task::TaskResult, worker::WorkerReference};
+
+// towers act at range with no movement and no multi-tick reservation - `find_task` re-scores
+// the room's priority ladder fresh every tick, so these handlers just fire the one intent and
+// hand back `Complete` unconditionally rather than tracking any progress of their own.
+pub fn attack_with_tower(worker: &WorkerReference, id: &ObjectId<Creep>) -> TaskResult {
+    if let (Some(tower), Some(creep)) = (worker.as_tower(), id.resolve()) {
+        let _ = tower.attack(&creep);
+    }
+    TaskResult::Complete
+}
+
+pub fn heal_with_tower(worker: &WorkerReference, id: &ObjectId<Creep>) -> TaskResult {
+    if let (Some(tower), Some(creep)) = (worker.as_tower(), id.resolve()) {
+        let _ = tower.heal(&creep);
+    }
+    TaskResult::Complete
+}
+
+pub fn repair_with_tower(worker: &WorkerReference, id: &ObjectId<Structure>) -> TaskResult {
+    if let (Some(tower), Some(structure)) = (worker.as_tower(), id.resolve()) {
+        let _ = tower.repair(&structure);
+    }
+    TaskResult::Complete
+}